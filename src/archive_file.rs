@@ -0,0 +1,124 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::Archive;
+
+/**
+A handle to a txtar file on disk that caches the parsed archive.
+
+The file is re-parsed whenever its modification time changes, which
+makes this handle suitable for long-running tools that repeatedly
+consult the same fixture archive.
+
+# Examples
+
+```rust no_run
+use txtar::ArchiveFile;
+
+let mut handle = ArchiveFile::open("fixtures/basic.txtar").unwrap();
+let archive = handle.archive().unwrap();
+archive.materialize("/tmp/somedir/").unwrap();
+```
+**/
+#[derive(Debug)]
+pub struct ArchiveFile {
+    path: PathBuf,
+    modified: SystemTime,
+    archive: Archive,
+}
+
+impl ArchiveFile {
+    /// Opens and parses the txtar file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the file cannot be read or if its
+    /// contents are not valid UTF-8.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ArchiveFile> {
+        let path = path.as_ref().to_owned();
+        let (modified, archive) = read(&path)?;
+
+        Ok(ArchiveFile {
+            path,
+            modified,
+            archive,
+        })
+    }
+
+    /// Returns the path of the underlying file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the archive, re-parsing the underlying file first if its
+    /// modification time changed since it was last read.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the file cannot be inspected or
+    /// re-read. The previously cached archive is kept in that case.
+    pub fn archive(&mut self) -> io::Result<&Archive> {
+        let modified = fs::metadata(&self.path)?.modified()?;
+        if modified != self.modified {
+            self.reload()?;
+        }
+
+        Ok(&self.archive)
+    }
+
+    /// Unconditionally re-parses the underlying file.
+    pub fn reload(&mut self) -> io::Result<()> {
+        let (modified, archive) = read(&self.path)?;
+        self.modified = modified;
+        self.archive = archive;
+
+        Ok(())
+    }
+}
+
+fn read(path: &Path) -> io::Result<(SystemTime, Archive)> {
+    let mut file = fs::File::open(path)?;
+    let modified = file.metadata()?.modified()?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let mut archive =
+        Archive::try_from(&bytes[..]).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    archive.set_origin(path);
+
+    Ok((modified, archive))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::{prelude::*, TempDir};
+    use similar_asserts::assert_eq;
+    use std::time::Duration;
+
+    #[test]
+    fn reload_on_mtime_change() {
+        let dir = TempDir::new().unwrap();
+        let child = dir.child("a.txtar");
+        child.write_str("-- a --\nfirst").unwrap();
+
+        let mut handle = ArchiveFile::open(&child).unwrap();
+        assert_eq!(handle.archive().unwrap().to_string(), "-- a --\nfirst\n");
+
+        child.write_str("-- a --\nsecond").unwrap();
+        let later = handle.modified + Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&child)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        let archive = handle.archive().unwrap();
+        assert_eq!(archive.to_string(), "-- a --\nsecond\n");
+        assert_eq!(archive.files[0].origin(), Some(child.path()));
+    }
+}
@@ -0,0 +1,286 @@
+use std::path::PathBuf;
+
+use crate::{Archive, File};
+
+/// Limits on the size of an archive. Sizes are in bytes of file data.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Budget {
+    /// The maximum combined size of all files.
+    pub max_total: Option<usize>,
+    /// The maximum size of any single file.
+    pub max_per_file: Option<usize>,
+    /// The maximum number of files.
+    pub max_files: Option<usize>,
+}
+
+/// A way in which an archive exceeds a [`Budget`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BudgetViolation {
+    Total {
+        size: usize,
+        max: usize,
+    },
+    File {
+        name: PathBuf,
+        size: usize,
+        max: usize,
+    },
+    Files {
+        count: usize,
+        max: usize,
+    },
+}
+
+impl Archive {
+    /// Checks the archive against the budget, returning every violation.
+    /// An empty result means the archive is within budget.
+    pub fn enforce_budget(&self, budget: Budget) -> Vec<BudgetViolation> {
+        let mut violations = Vec::new();
+
+        if let Some(max) = budget.max_files {
+            let count = self.files.len();
+            if count > max {
+                violations.push(BudgetViolation::Files { count, max });
+            }
+        }
+        if let Some(max) = budget.max_per_file {
+            for File { name, data, .. } in &self.files {
+                if data.len() > max {
+                    violations.push(BudgetViolation::File {
+                        name: name.clone(),
+                        size: data.len(),
+                        max,
+                    });
+                }
+            }
+        }
+        if let Some(max) = budget.max_total {
+            let size = total_size(&self.files);
+            if size > max {
+                violations.push(BudgetViolation::Total { size, max });
+            }
+        }
+
+        violations
+    }
+
+    /// Returns a copy of the archive that fits the budget, along with the
+    /// violations of the original archive.
+    ///
+    /// Files beyond `max_files` are dropped, files larger than
+    /// `max_per_file` are truncated, and then the largest remaining files
+    /// are truncated until the total fits `max_total`. Truncated files
+    /// still end with a newline.
+    pub fn trim_to_budget(&self, budget: Budget) -> (Archive, Vec<BudgetViolation>) {
+        let violations = self.enforce_budget(budget);
+        let max_files = budget.max_files.unwrap_or(usize::MAX);
+        let max_per_file = budget.max_per_file.unwrap_or(usize::MAX);
+
+        let mut files: Vec<File> = self
+            .files
+            .iter()
+            .take(max_files)
+            .map(|f| File {
+                name: f.name.clone(),
+                data: truncate(&f.data, max_per_file),
+                metadata: f.metadata.clone(),
+                origin: f.origin.clone(),
+                added_newline: f.added_newline,
+            })
+            .collect();
+
+        if let Some(max) = budget.max_total {
+            loop {
+                let excess = total_size(&files).saturating_sub(max);
+                if excess == 0 {
+                    break;
+                }
+                let largest = files.iter_mut().max_by_key(|f| f.data.len()).unwrap();
+                let len = largest.data.len().saturating_sub(excess);
+                largest.data = truncate(&largest.data, len);
+            }
+        }
+
+        (Archive::from_parts(&self.comment, files), violations)
+    }
+
+    /// Returns an iterator over the files that stops at the first file
+    /// exceeding the limits, yielding the violation in its place.
+    ///
+    /// This allows processing the beginning of untrusted archives of any
+    /// size: no more than `max_files` files and `max_total` bytes are
+    /// yielded, and no file larger than `max_per_file`.
+    pub fn iter_limited(&self, limits: Budget) -> Limited<'_> {
+        Limited {
+            files: self.files.iter(),
+            limits,
+            count: 0,
+            size: 0,
+            done: false,
+        }
+    }
+}
+
+/// An iterator over the files of an archive within a [`Budget`].
+///
+/// Returned by [`Archive::iter_limited`].
+#[derive(Debug, Clone)]
+pub struct Limited<'a> {
+    files: std::slice::Iter<'a, File>,
+    limits: Budget,
+    count: usize,
+    size: usize,
+    done: bool,
+}
+
+impl<'a> Iterator for Limited<'a> {
+    type Item = Result<&'a File, BudgetViolation>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let file = self.files.next()?;
+        let size = self.size + file.data.len();
+
+        let violation = match self.limits {
+            Budget {
+                max_files: Some(max),
+                ..
+            } if self.count >= max => BudgetViolation::Files {
+                count: self.count + 1 + self.files.len(),
+                max,
+            },
+            Budget {
+                max_per_file: Some(max),
+                ..
+            } if file.data.len() > max => BudgetViolation::File {
+                name: file.name.clone(),
+                size: file.data.len(),
+                max,
+            },
+            Budget {
+                max_total: Some(max),
+                ..
+            } if size > max => BudgetViolation::Total { size, max },
+            _ => {
+                self.count += 1;
+                self.size = size;
+                return Some(Ok(file));
+            }
+        };
+
+        self.done = true;
+        Some(Err(violation))
+    }
+}
+
+fn total_size(files: &[File]) -> usize {
+    files.iter().map(|f| f.data.len()).sum()
+}
+
+/// Truncates `data` to at most `max` bytes, keeping the trailing newline.
+fn truncate(data: &str, max: usize) -> String {
+    if data.len() <= max {
+        return data.to_owned();
+    }
+    let mut end = max.saturating_sub(1);
+    while !data.is_char_boundary(end) {
+        end -= 1;
+    }
+    if end == 0 {
+        return String::new();
+    }
+
+    let mut data = data[..end].to_owned();
+    data.push('\n');
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn budget() {
+        let archive = Archive::from("-- a --\n0123456789\n-- b --\nxyz\n-- c --\nhello\n");
+        let budget = Budget {
+            max_total: Some(12),
+            max_per_file: Some(8),
+            max_files: Some(2),
+        };
+
+        assert_eq!(
+            archive.enforce_budget(budget),
+            [
+                BudgetViolation::Files { count: 3, max: 2 },
+                BudgetViolation::File {
+                    name: "a".into(),
+                    size: 11,
+                    max: 8,
+                },
+                BudgetViolation::Total { size: 21, max: 12 },
+            ]
+        );
+
+        let (trimmed, violations) = archive.trim_to_budget(budget);
+        assert_eq!(violations.len(), 3);
+        assert_eq!(trimmed.to_string(), "-- a --\n0123456\n-- b --\nxyz\n");
+        assert!(trimmed.enforce_budget(budget).is_empty());
+
+        let (trimmed, _) = archive.trim_to_budget(Budget {
+            max_total: Some(5),
+            ..Budget::default()
+        });
+        assert_eq!(trimmed.to_string(), "-- a --\n-- b --\nxyz\n-- c --\n");
+    }
+
+    #[test]
+    fn iter_limited() {
+        let archive = Archive::from("-- a --\n0123456789\n-- b --\nxyz\n-- c --\nhello\n");
+        let names = |budget| {
+            archive
+                .iter_limited(budget)
+                .map(|f| f.map(|f| f.name().display().to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(Budget::default()),
+            [Ok("a".into()), Ok("b".into()), Ok("c".into())]
+        );
+        assert_eq!(
+            names(Budget {
+                max_files: Some(1),
+                ..Budget::default()
+            }),
+            [
+                Ok("a".into()),
+                Err(BudgetViolation::Files { count: 3, max: 1 })
+            ]
+        );
+        assert_eq!(
+            names(Budget {
+                max_total: Some(16),
+                ..Budget::default()
+            }),
+            [
+                Ok("a".into()),
+                Ok("b".into()),
+                Err(BudgetViolation::Total { size: 21, max: 16 })
+            ]
+        );
+        assert_eq!(
+            names(Budget {
+                max_per_file: Some(8),
+                ..Budget::default()
+            }),
+            [Err(BudgetViolation::File {
+                name: "a".into(),
+                size: 11,
+                max: 8
+            })]
+        );
+    }
+}
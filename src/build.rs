@@ -0,0 +1,153 @@
+/*!
+Helpers for using archives from build scripts.
+
+# Examples
+
+```rust no_run
+// in build.rs
+let root = txtar::build::materialize_out_dir("fixtures/site.txtar", "site").unwrap();
+println!("cargo:rustc-env=SITE_ROOT={}", root.display());
+```
+!*/
+
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{Archive, MaterializeError};
+
+/// Materializes the txtar file at `archive` into `subdir` of the build
+/// script's `OUT_DIR` and returns the path of that directory.
+///
+/// Any previous contents of the directory are removed first, so running
+/// the build script again yields the same tree. The
+/// `cargo:rerun-if-changed` line for the archive is printed to stdout.
+///
+/// # Errors
+///
+/// This function will error if `OUT_DIR` is not set, if `subdir` is
+/// empty or not a relative path below `OUT_DIR`, if the archive cannot be
+/// read or is not valid UTF-8, or if materializing it fails.
+pub fn materialize_out_dir<P, Q>(archive: P, subdir: Q) -> Result<PathBuf, MaterializeError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let out_dir = env::var_os("OUT_DIR")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+
+    materialize_into(
+        Path::new(&out_dir),
+        archive.as_ref(),
+        subdir.as_ref(),
+        &mut io::stdout(),
+    )
+}
+
+fn materialize_into<W: Write>(
+    out_dir: &Path,
+    archive: &Path,
+    subdir: &Path,
+    cargo: &mut W,
+) -> Result<PathBuf, MaterializeError> {
+    check_subdir(subdir)?;
+    writeln!(cargo, "cargo:rerun-if-changed={}", archive.display())?;
+
+    let bytes = fs::read(archive)?;
+    let archive =
+        Archive::try_from(&bytes[..]).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let root = out_dir.join(subdir);
+    match fs::remove_dir_all(&root) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    archive.materialize(&root)?;
+
+    Ok(root)
+}
+
+/// Checks that `subdir` names a directory strictly below `OUT_DIR`, as
+/// it is removed before materializing.
+fn check_subdir(subdir: &Path) -> Result<(), MaterializeError> {
+    let rejected = |reason| MaterializeError::Rejected(subdir.display().to_string(), reason);
+    let mut normal = false;
+    for component in subdir.components() {
+        match component {
+            Component::Normal(_) => normal = true,
+            Component::CurDir => {}
+            Component::ParentDir => return Err(rejected("subdirectory must not contain `..`")),
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(rejected("subdirectory must be a relative path"))
+            }
+        }
+    }
+    if !normal {
+        return Err(rejected("subdirectory must not be empty"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::{prelude::*, TempDir};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn materialize_twice() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.child("site.txtar");
+        source.write_str("-- index.html --\nhello").unwrap();
+        let out_dir = dir.child("out");
+
+        for _ in 0..2 {
+            let mut cargo = Vec::new();
+            let root = materialize_into(&out_dir, &source, Path::new("site"), &mut cargo).unwrap();
+
+            assert_eq!(root, out_dir.join("site"));
+            assert_eq!(
+                String::from_utf8(cargo).unwrap(),
+                format!("cargo:rerun-if-changed={}\n", source.display())
+            );
+            out_dir.child("site/index.html").assert("hello\n");
+        }
+    }
+
+    #[test]
+    fn reject_subdir() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.child("site.txtar");
+        source.write_str("-- index.html --\nhello").unwrap();
+        let out_dir = dir.child("out");
+        let keep = dir.child("keep");
+        keep.write_str("keep").unwrap();
+        out_dir.child("keep").write_str("keep").unwrap();
+
+        let cases = [
+            ("", "subdirectory must not be empty"),
+            (".", "subdirectory must not be empty"),
+            ("../keep", "subdirectory must not contain `..`"),
+            ("site/../..", "subdirectory must not contain `..`"),
+        ];
+        let absolute = keep.path().display().to_string();
+        let cases = cases
+            .into_iter()
+            .chain([(absolute.as_str(), "subdirectory must be a relative path")]);
+        for (subdir, reason) in cases {
+            let mut cargo = Vec::new();
+            let err =
+                materialize_into(&out_dir, &source, Path::new(subdir), &mut cargo).unwrap_err();
+            assert!(
+                matches!(err, MaterializeError::Rejected(_, r) if r == reason),
+                "materialize_into[{subdir}]"
+            );
+            assert!(cargo.is_empty());
+        }
+        keep.assert("keep");
+        out_dir.child("keep").assert("keep");
+    }
+}
@@ -0,0 +1,208 @@
+use std::{io, path::Path, process::Command};
+
+use crate::{Archive, File, NameRules};
+
+/**
+A builder for archives assembled from several sources.
+
+Errors are deferred: the first one is returned by [`ArchiveBuilder::build`]
+and later steps are skipped.
+
+# Examples
+
+```rust
+use txtar::ArchiveBuilder;
+
+let archive = ArchiveBuilder::new()
+    .comment("fixture")
+    .file("a.txt", "hello")
+    .file_bytes("b.txt", b"world\n")
+    .build()
+    .unwrap();
+assert_eq!(
+    archive.to_string(),
+    "fixture\n-- a.txt --\nhello\n-- b.txt --\nworld\n"
+);
+```
+
+Files can also be created from the output of commands:
+
+```rust no_run
+use std::process::Command;
+use txtar::ArchiveBuilder;
+
+let mut rustc = Command::new("rustc");
+rustc.arg("--version");
+
+let archive = ArchiveBuilder::new()
+    .capture_stderr(true)
+    .file_from_command("rustc-version", rustc)
+    .build()
+    .unwrap();
+```
+**/
+#[derive(Debug, Default)]
+pub struct ArchiveBuilder {
+    archive: Archive,
+    capture_stderr: bool,
+    name_rules: Option<NameRules>,
+    error: Option<io::Error>,
+}
+
+impl ArchiveBuilder {
+    pub fn new() -> ArchiveBuilder {
+        ArchiveBuilder::default()
+    }
+
+    /// Sets the comment of the archive.
+    pub fn comment(mut self, comment: &str) -> ArchiveBuilder {
+        self.archive.set_comment(comment);
+        self
+    }
+
+    /// Sets rules that the names of files added afterwards must follow.
+    pub fn name_rules(mut self, rules: NameRules) -> ArchiveBuilder {
+        self.name_rules = Some(rules);
+        self
+    }
+
+    /// Adds a file with the given data.
+    pub fn file<P: AsRef<Path>>(mut self, name: P, data: &str) -> ArchiveBuilder {
+        self.push(File::new(name, data));
+        self
+    }
+
+    /// Adds a file with the given data, which must be valid UTF-8.
+    pub fn file_bytes<P: AsRef<Path>>(mut self, name: P, data: &[u8]) -> ArchiveBuilder {
+        if self.error.is_some() {
+            return self;
+        }
+        match std::str::from_utf8(data) {
+            Ok(data) => self.file(name, data),
+            Err(e) => {
+                self.error = Some(io::Error::new(io::ErrorKind::InvalidData, e));
+                self
+            }
+        }
+    }
+
+    /// Sets whether commands added afterwards also have their standard
+    /// error captured, as a sibling file whose name ends in `.stderr`.
+    pub fn capture_stderr(mut self, capture_stderr: bool) -> ArchiveBuilder {
+        self.capture_stderr = capture_stderr;
+        self
+    }
+
+    /// Runs the command and adds its standard output as a file. The
+    /// command's exit status is not checked, so the output of failing
+    /// commands is captured too. Output that is not valid UTF-8 is
+    /// converted lossily.
+    pub fn file_from_command<P: AsRef<Path>>(
+        mut self,
+        name: P,
+        mut command: Command,
+    ) -> ArchiveBuilder {
+        if self.error.is_some() {
+            return self;
+        }
+        let output = match command.output() {
+            Ok(output) => output,
+            Err(e) => {
+                self.error = Some(e);
+                return self;
+            }
+        };
+
+        let name = name.as_ref();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        self.push(File::new(name, &stdout));
+        if self.capture_stderr {
+            let mut stderr_name = name.as_os_str().to_owned();
+            stderr_name.push(".stderr");
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            self.push(File::new(stderr_name, &stderr));
+        }
+
+        self
+    }
+
+    /// Adds the file, unless an earlier step failed or its name breaks
+    /// the name rules.
+    fn push(&mut self, file: File) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Some(Err(reason)) = self.name_rules.as_ref().map(|r| r.check(&file.name)) {
+            let msg = format!("{}: {reason}", file.name.display());
+            self.error = Some(io::Error::new(io::ErrorKind::InvalidInput, msg));
+            return;
+        }
+        self.archive.push(file);
+    }
+
+    /// Returns the archive.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered while adding
+    /// files, such as a command that could not be started.
+    pub fn build(self) -> io::Result<Archive> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.archive),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn build_files() {
+        let archive = ArchiveBuilder::new()
+            .file("a", "1")
+            .comment("first")
+            .comment("second\n")
+            .file_bytes("b", b"2\n")
+            .build()
+            .unwrap();
+        assert_eq!(archive.to_string(), "second\n-- a --\n1\n-- b --\n2\n");
+
+        let err = ArchiveBuilder::new()
+            .file_bytes("bin", b"\xff")
+            .build()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(unix)]
+    fn sh(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_from_command() {
+        let archive = ArchiveBuilder::new()
+            .file_from_command("out", sh("echo hello; echo oops >&2"))
+            .capture_stderr(true)
+            .file_from_command("fail", sh("echo partial; echo broken >&2; exit 3"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            archive.to_string(),
+            "-- out --\nhello\n-- fail --\npartial\n-- fail.stderr --\nbroken\n"
+        );
+
+        let err = ArchiveBuilder::new()
+            .file_from_command("missing", Command::new("txtar-surely-missing"))
+            .file_from_command("out", sh("echo hello"))
+            .build()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,236 @@
+/*!
+Collecting support bundles.
+
+A [`Collector`] gathers files, command output and environment variables
+into one archive that users can attach to bug reports.
+
+# Examples
+
+```rust no_run
+use std::process::Command;
+use txtar::{bundle::Collector, Budget};
+
+let mut version = Command::new("cargo");
+version.arg("--version");
+
+let (archive, _) = Collector::new()
+    .file("Cargo.toml")
+    .command("cargo-version", version)
+    .env(["RUST_LOG", "CARGO_HOME"])
+    .budget(Budget {
+        max_total: Some(1 << 20),
+        ..Budget::default()
+    })
+    .collect()
+    .unwrap();
+println!("{archive}");
+```
+!*/
+
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+#[cfg(feature = "secrets")]
+use crate::SecretScanner;
+use crate::{Archive, ArchiveBuilder, Budget, BudgetViolation, File, Meta};
+
+/// A builder that gathers the contents of a support bundle.
+///
+/// Like [`ArchiveBuilder`], it defers errors until
+/// [`Collector::collect`].
+#[derive(Debug, Default)]
+pub struct Collector {
+    archive: Archive,
+    budget: Budget,
+    #[cfg(feature = "secrets")]
+    scanner: Option<SecretScanner>,
+    error: Option<io::Error>,
+}
+
+impl Collector {
+    pub fn new() -> Collector {
+        Collector::default()
+    }
+
+    /// Adds the file at the given path, named after the path without
+    /// its root. Contents that are not valid UTF-8 are converted lossily.
+    pub fn file<P: AsRef<Path>>(mut self, path: P) -> Collector {
+        let path = path.as_ref();
+        let name: PathBuf = path
+            .components()
+            .filter(|c| !matches!(c, Component::Prefix(_) | Component::RootDir))
+            .collect();
+        let result = self.add_file(&name, path);
+        self.defer(result);
+        self
+    }
+
+    /// Adds every file below `root` whose path relative to `root`
+    /// matches the glob, named after that relative path.
+    #[cfg(feature = "globset")]
+    pub fn glob<P: AsRef<Path>>(mut self, root: P, glob: &str) -> Collector {
+        let result = crate::glob::matcher(glob)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+            .and_then(|matcher| {
+                self.walk(root.as_ref(), Path::new(""), &mut |rel| {
+                    matcher.is_match(rel)
+                })
+            });
+        self.defer(result);
+        self
+    }
+
+    /// Runs the command and adds its standard output and standard error
+    /// as in [`ArchiveBuilder::file_from_command`].
+    pub fn command<P: AsRef<Path>>(mut self, name: P, command: Command) -> Collector {
+        let result = ArchiveBuilder::new()
+            .capture_stderr(true)
+            .file_from_command(name, command)
+            .build()
+            .map(|archive| self.archive.files.extend(archive.files));
+        self.defer(result);
+        self
+    }
+
+    /// Adds a [`Meta`] member with the current platform and the given
+    /// environment variables.
+    pub fn env<I, S>(mut self, vars: I) -> Collector
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let meta = Meta::capture(vars);
+        self.archive.push(File::new(Meta::PATH, &meta.to_string()));
+        self
+    }
+
+    /// Sets the limits the bundle is trimmed to.
+    pub fn budget(mut self, budget: Budget) -> Collector {
+        self.budget = budget;
+        self
+    }
+
+    /// Sets the scanner used to redact secrets from the bundle.
+    #[cfg(feature = "secrets")]
+    pub fn redact(mut self, scanner: SecretScanner) -> Collector {
+        self.scanner = Some(scanner);
+        self
+    }
+
+    /// Returns the bundle, redacted and trimmed to the budget, along with
+    /// the ways in which the untrimmed bundle exceeded the budget.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the first error encountered while adding
+    /// files, such as a file that could not be read.
+    pub fn collect(self) -> io::Result<(Archive, Vec<BudgetViolation>)> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        #[cfg(feature = "secrets")]
+        let archive = match &self.scanner {
+            Some(scanner) => self.archive.redact_with(scanner),
+            None => self.archive,
+        };
+        #[cfg(not(feature = "secrets"))]
+        let archive = self.archive;
+
+        Ok(archive.trim_to_budget(self.budget))
+    }
+
+    fn add_file(&mut self, name: &Path, path: &Path) -> io::Result<()> {
+        let bytes = fs::read(path)?;
+        let mut file = File::new(name, &String::from_utf8_lossy(&bytes));
+        file.set_origin(path);
+        self.archive.push(file);
+        Ok(())
+    }
+
+    #[cfg(feature = "globset")]
+    fn walk(
+        &mut self,
+        root: &Path,
+        rel: &Path,
+        matches: &mut dyn FnMut(&Path) -> bool,
+    ) -> io::Result<()> {
+        let mut entries = fs::read_dir(root.join(rel))?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|e| e.file_name());
+
+        for entry in entries {
+            let rel = rel.join(entry.file_name());
+            let file_type = fs::metadata(entry.path())?.file_type();
+            if file_type.is_dir() {
+                self.walk(root, &rel, matches)?;
+            } else if file_type.is_file() && matches(&rel) {
+                self.add_file(&rel, &entry.path())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn defer(&mut self, result: io::Result<()>) {
+        if let Err(e) = result {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::{prelude::*, TempDir};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn collect_bundle() {
+        let dir = TempDir::new().unwrap();
+        dir.child("app.log").write_str("started\nfailed\n").unwrap();
+        dir.child("conf/a.toml").write_str("key = 1\n").unwrap();
+        dir.child("conf/b.json").write_str("{}\n").unwrap();
+
+        let collector = Collector::new()
+            .file(dir.child("app.log").path())
+            .env(["PATH"])
+            .budget(Budget {
+                max_per_file: Some(8),
+                ..Budget::default()
+            });
+        #[cfg(feature = "globset")]
+        let collector = collector.glob(&dir, "**/*.toml");
+        let (archive, violations) = collector.collect().unwrap();
+
+        let log = archive.files.iter().find(|f| f.name.ends_with("app.log"));
+        assert_eq!(log.unwrap().data, "started\n");
+        assert!(archive.contains(Meta::PATH));
+        assert!(!violations.is_empty());
+        #[cfg(feature = "globset")]
+        assert_eq!(archive.get("conf/a.toml").unwrap().data, "key = 1\n");
+
+        let err = Collector::new()
+            .file(dir.child("missing").path())
+            .collect()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[cfg(feature = "secrets")]
+    #[test]
+    fn collect_redacted() {
+        let dir = TempDir::new().unwrap();
+        dir.child("env").write_str("TOKEN=hunter2\n").unwrap();
+
+        let scanner = SecretScanner::empty().pattern("token", "hunter2").unwrap();
+        let (archive, _) = Collector::new()
+            .file(dir.child("env").path())
+            .redact(scanner)
+            .collect()
+            .unwrap();
+        assert_eq!(archive.files[0].data, "TOKEN=[REDACTED:token]\n");
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs,
+    hash::Hasher,
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{Archive, File, MaterializeError, MaterializeOptions};
+
+/// The 64-bit FNV-1a hash, which unlike the standard library's hashers is
+/// the same on every platform and Rust version.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Returns the FNV-1a hash of the data as 16 hexadecimal digits.
+pub(crate) fn data_hash(data: &str) -> String {
+    let mut hasher = Fnv1a::default();
+    hasher.write(data.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+impl Archive {
+    /// Returns a hash of the txtar serialization of the archive that is
+    /// stable across platforms, processes and crate versions, as 16
+    /// hexadecimal digits.
+    ///
+    /// The digest is the 64-bit FNV-1a hash of the text written by
+    /// [`Archive::to_writer`], so it changes exactly when that text does.
+    /// It is meant for cache keys, not for detecting tampering.
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Fnv1a::default();
+        self.hash_serialized(|bytes| hasher.write(bytes));
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Writes the archive to a directory below `cache_root` named after
+    /// its [content hash](Archive::content_hash), unless that directory
+    /// already exists, and returns the path of the directory.
+    ///
+    /// The files are written to a temporary directory that is then renamed
+    /// into place, so concurrent calls, even from separate processes,
+    /// never see a partially written directory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust no_run
+    /// use txtar::Archive;
+    ///
+    /// let archive = Archive::from("-- big.json --\n{}\n");
+    /// let dir = archive.materialize_cached("target/fixtures").unwrap();
+    /// assert!(dir.join("big.json").exists());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will error in the same cases as
+    /// [`Archive::materialize`].
+    pub fn materialize_cached<P: AsRef<Path>>(
+        &self,
+        cache_root: P,
+    ) -> Result<PathBuf, MaterializeError> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let cache_root = cache_root.as_ref();
+        let hash = self.content_hash();
+        let dir = cache_root.join(&hash);
+        if dir.is_dir() {
+            return Ok(dir);
+        }
+
+        fs::create_dir_all(cache_root)?;
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let tmp = cache_root.join(format!(".{hash}.{}-{n}.tmp", process::id()));
+        let result = self
+            .materialize_with(&tmp, &MaterializeOptions::new())
+            .and_then(|_| Ok(fs::rename(&tmp, &dir)?));
+        match result {
+            Ok(()) => Ok(dir),
+            Err(e) => {
+                // The error that stopped the extraction matters more than
+                // one cleaning up after it.
+                let _ = fs::remove_dir_all(&tmp);
+                match e {
+                    MaterializeError::Io(e) if renamed_over(&e) && dir.is_dir() => Ok(dir),
+                    e => Err(e),
+                }
+            }
+        }
+    }
+}
+
+impl File {
+    /// Returns a hash of the file as it appears in an archive, marker
+    /// line included, computed like [`Archive::content_hash`].
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Fnv1a::default();
+        hasher.write(self.to_string().as_bytes());
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// Returns `true` if a rename failed because its target already existed.
+fn renamed_over(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::AlreadyExists | io::ErrorKind::DirectoryNotEmpty
+    ) || cfg!(windows) && e.kind() == io::ErrorKind::PermissionDenied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::{prelude::*, TempDir};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn materialize_cached() {
+        let cache = TempDir::new().unwrap();
+        let archive = Archive::from("-- a --\nhello\n-- b/c --\n");
+        assert_eq!(archive.content_hash(), "431d51569765ef40");
+        let single = Archive::from("-- a --\nhello\n");
+        assert_eq!(archive.files[0].content_hash(), single.content_hash());
+
+        let dir = archive.materialize_cached(&cache).unwrap();
+        assert_eq!(dir, cache.child(archive.content_hash()).path());
+        cache.child("431d51569765ef40/a").assert("hello\n");
+
+        fs::write(dir.join("a"), "changed").unwrap();
+        assert_eq!(archive.materialize_cached(&cache).unwrap(), dir);
+        cache.child("431d51569765ef40/a").assert("changed");
+
+        let other = Archive::from("-- a --\n");
+        assert_ne!(other.materialize_cached(&cache).unwrap(), dir);
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 2);
+
+        let err = Archive::from("-- ../x --\n").materialize_cached(&cache);
+        assert!(matches!(err, Err(MaterializeError::DirEscape(_))));
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,410 @@
+use std::{
+    fmt,
+    ops::{Deref, DerefMut},
+    str::Lines,
+};
+
+use crate::{fix_newline, Archive, File};
+
+/// How to combine the comments of archives that are merged or split.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CommentPolicy {
+    /// Leave the resulting archive without a comment.
+    Drop,
+    /// Keep only the comment of the first archive.
+    KeepFirst,
+    /// Join the non-empty comments, in order, with the given separator
+    /// between them.
+    Concat(String),
+}
+
+impl CommentPolicy {
+    fn combine(&self, first: String, second: &str) -> String {
+        let mut comment = match self {
+            CommentPolicy::Drop => String::new(),
+            CommentPolicy::KeepFirst => first,
+            CommentPolicy::Concat(_) if first.is_empty() => second.to_owned(),
+            CommentPolicy::Concat(_) if second.is_empty() => first,
+            CommentPolicy::Concat(separator) => first + separator + second,
+        };
+        fix_newline(&mut comment);
+        comment
+    }
+}
+
+/// The comment of an archive, the text before its first file, which
+/// test frameworks often use for directives.
+///
+/// A comment is empty or ends with a newline, like the comment of an
+/// archive. It dereferences to [`str`].
+///
+/// # Examples
+///
+/// ```rust
+/// use txtar::{Archive, Comment};
+///
+/// let mut archive = Archive::from("Test.  \n\n-- a --\n");
+/// let mut comment = archive.take_comment();
+/// comment.normalize();
+/// comment.append("skip: windows");
+/// assert_eq!(comment.trimmed(), "Test.\nskip: windows");
+/// archive.set_comment(&comment);
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
+pub struct Comment(String);
+
+impl Comment {
+    /// Creates a comment from the given text, adding a final newline if
+    /// it needs one.
+    pub fn new(text: &str) -> Comment {
+        Comment::from(text.to_owned())
+    }
+
+    /// Returns the comment as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns an iterator over the lines of the comment.
+    pub fn lines(&self) -> Lines<'_> {
+        self.0.lines()
+    }
+
+    /// Returns the comment without leading and trailing whitespace, which
+    /// includes the final newline.
+    pub fn trimmed(&self) -> &str {
+        self.0.trim()
+    }
+
+    /// Appends a line to the comment. Line breaks in it are replaced by
+    /// spaces.
+    pub fn append(&mut self, line: &str) {
+        self.0.push_str(&line.replace(['\r', '\n'], " "));
+        self.0.push('\n');
+    }
+
+    /// Strips trailing whitespace from each line and removes trailing
+    /// blank lines.
+    pub fn normalize(&mut self) {
+        let lines: Vec<&str> = self.0.trim_end().lines().map(str::trim_end).collect();
+        self.0 = join_lines(&lines);
+    }
+
+    /// Returns the comment as a `String`.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl From<String> for Comment {
+    fn from(mut text: String) -> Comment {
+        fix_newline(&mut text);
+        Comment(text)
+    }
+}
+
+impl From<&str> for Comment {
+    fn from(text: &str) -> Comment {
+        Comment::new(text)
+    }
+}
+
+impl Deref for Comment {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Comment {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Comment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A mutable reference to the comment of an archive, which adds a final
+/// newline to the comment when it is dropped if it needs one.
+///
+/// This struct is created by [`Archive::comment_mut`].
+#[derive(Debug)]
+pub struct CommentMut<'a> {
+    comment: &'a mut String,
+}
+
+impl Deref for CommentMut<'_> {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        self.comment
+    }
+}
+
+impl DerefMut for CommentMut<'_> {
+    fn deref_mut(&mut self) -> &mut String {
+        self.comment
+    }
+}
+
+impl Drop for CommentMut<'_> {
+    fn drop(&mut self) {
+        fix_newline(self.comment);
+    }
+}
+
+impl Archive {
+    /// Replaces the comment, returning the archive for chaining.
+    pub fn with_comment(mut self, comment: &str) -> Archive {
+        self.set_comment(comment);
+        self
+    }
+
+    /// Appends the files of `other`, combining the comments according to
+    /// the policy.
+    pub fn append(&mut self, other: Archive, comments: &CommentPolicy) {
+        let comment = std::mem::take(&mut self.comment);
+        self.comment = comments.combine(comment, &other.comment);
+        self.files.extend(other.files);
+        self.reindex();
+    }
+
+    /// Returns an archive of the files for which the predicate returns
+    /// `true`. The comment is kept unless the policy is
+    /// [`CommentPolicy::Drop`].
+    pub fn subset<F>(self, predicate: F, comments: &CommentPolicy) -> Archive
+    where
+        F: FnMut(&File) -> bool,
+    {
+        Archive {
+            comment: comments.combine(self.comment, ""),
+            files: self.files.into_iter().filter(predicate).collect(),
+            ..Archive::default()
+        }
+    }
+
+    /// Returns the comment.
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// Replaces the comment.
+    pub fn set_comment(&mut self, comment: &str) {
+        self.comment = comment.to_owned();
+        fix_newline(&mut self.comment);
+    }
+
+    /// Appends a line to the comment, such as a note on how the archive
+    /// was generated, as in [`Comment::append`].
+    pub fn append_comment_line(&mut self, line: &str) {
+        let mut comment = self.take_comment();
+        comment.append(line);
+        self.comment = comment.into_string();
+    }
+
+    /// Returns a mutable reference to the comment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use txtar::Archive;
+    ///
+    /// let mut archive = Archive::from("-- a --\n");
+    /// archive.comment_mut().push_str("generated");
+    /// assert_eq!(archive.to_string(), "generated\n-- a --\n");
+    /// ```
+    pub fn comment_mut(&mut self) -> CommentMut<'_> {
+        CommentMut {
+            comment: &mut self.comment,
+        }
+    }
+
+    /// Removes the comment and returns it, leaving the archive without one.
+    pub fn take_comment(&mut self) -> Comment {
+        Comment(std::mem::take(&mut self.comment))
+    }
+
+    /// Strips trailing whitespace from each line of the comment and
+    /// removes trailing blank lines, as in [`Comment::normalize`].
+    pub fn normalize_comment(&mut self) {
+        let mut comment = self.take_comment();
+        comment.normalize();
+        self.comment = comment.into_string();
+    }
+
+    /// Returns the comment as bytes.
+    pub fn comment_bytes(&self) -> &[u8] {
+        self.comment.as_bytes()
+    }
+
+    /// Returns an iterator over the lines of the comment.
+    pub fn comment_lines(&self) -> Lines<'_> {
+        self.comment.lines()
+    }
+
+    /// Returns an iterator over the `key: value` lines of the comment.
+    ///
+    /// Keys must not contain whitespace. Keys and values are trimmed.
+    pub fn comment_values(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.comment.lines().filter_map(key_value)
+    }
+
+    /// Returns the value of the first `key: value` line of the comment
+    /// with the given key.
+    pub fn comment_value(&self, key: &str) -> Option<&str> {
+        self.comment_values()
+            .find_map(|(k, v)| (k == key).then_some(v))
+    }
+
+    /// Sets the value of the first `key: value` line of the comment with
+    /// the given key, appending a new line if there is none. The value is
+    /// trimmed and line breaks in it are replaced by spaces.
+    pub fn set_comment_value(&mut self, key: &str, value: &str) {
+        let line = format!("{key}: {}", value.trim().replace(['\r', '\n'], " "));
+        let mut lines: Vec<&str> = self.comment.lines().collect();
+        match lines
+            .iter()
+            .position(|l| key_value(l).is_some_and(|(k, _)| k == key))
+        {
+            Some(i) => lines[i] = &line,
+            None => lines.push(&line),
+        }
+
+        self.comment = join_lines(&lines);
+    }
+
+    /// Removes every `key: value` line of the comment with the given key,
+    /// returning the value of the first one.
+    pub fn remove_comment_value(&mut self, key: &str) -> Option<String> {
+        let value = self.comment_value(key)?.to_owned();
+        let lines: Vec<&str> = self
+            .comment
+            .lines()
+            .filter(|l| key_value(l).map(|(k, _)| k) != Some(key))
+            .collect();
+        self.comment = join_lines(&lines);
+
+        Some(value)
+    }
+}
+
+fn key_value(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((key, value.trim()))
+}
+
+fn join_lines(lines: &[&str]) -> String {
+    lines.iter().flat_map(|l| [*l, "\n"]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+    use std::path::Path;
+
+    #[test]
+    fn comment_values() {
+        let mut archive =
+            Archive::from("Regression test.\nskip: windows\nowner : alice\n-- a --\n");
+        assert_eq!(
+            archive.comment_lines().collect::<Vec<_>>(),
+            ["Regression test.", "skip: windows", "owner : alice"]
+        );
+        assert_eq!(
+            archive.comment_values().collect::<Vec<_>>(),
+            [("skip", "windows"), ("owner", "alice")]
+        );
+        assert_eq!(archive.comment_value("owner"), Some("alice"));
+
+        archive.set_comment_value("skip", "macos");
+        archive.set_comment_value("timeout", "30s\n");
+        assert_eq!(
+            archive.remove_comment_value("owner").as_deref(),
+            Some("alice")
+        );
+        assert_eq!(archive.remove_comment_value("owner"), None);
+        assert_eq!(
+            archive.comment(),
+            "Regression test.\nskip: macos\ntimeout: 30s\n"
+        );
+    }
+
+    #[test]
+    fn comment_mutation() {
+        let mut archive = Archive::from("-- a --\n");
+        archive.set_comment("Generated test.");
+        archive.append_comment_line("by: gen\non commit abc");
+        assert_eq!(
+            archive.comment(),
+            "Generated test.\nby: gen on commit abc\n"
+        );
+
+        let mut comment = archive.comment_mut();
+        comment.truncate(6);
+        assert_eq!(*comment, "Genera");
+        drop(comment);
+        assert_eq!(archive.to_string(), "Genera\n-- a --\n");
+
+        archive.set_comment("");
+        archive.comment_mut().clear();
+        assert_eq!(archive.comment(), "");
+    }
+
+    #[test]
+    fn comment_type() {
+        let mut comment = Comment::new("\n  Directive: x \t\n\nnext  \n \n");
+        assert_eq!(comment.lines().count(), 5);
+        assert_eq!(comment.trimmed(), "Directive: x \t\n\nnext");
+        comment.normalize();
+        assert_eq!(comment.as_str(), "\n  Directive: x\n\nnext\n");
+        comment.append("a\nb");
+        assert_eq!(comment.to_string(), "\n  Directive: x\n\nnext\na b\n");
+
+        assert_eq!(Comment::from("no newline").as_str(), "no newline\n");
+        let mut blank = Comment::new(" \n\n");
+        blank.normalize();
+        assert!(blank.is_empty());
+
+        let mut archive = Archive::from("keep  \n\n-- a --\n");
+        archive.normalize_comment();
+        assert_eq!(archive.comment(), "keep\n");
+        assert_eq!(archive.take_comment(), Comment::new("keep"));
+        assert_eq!(archive.comment(), "");
+    }
+
+    #[test]
+    fn comment_policies() {
+        let base = || Archive::from("base\n-- a --\n-- b --\n");
+        let overlay = || Archive::from("overlay\n-- c --\n");
+        let merge = |policy| {
+            let mut archive = base();
+            archive.append(overlay(), &policy);
+            archive.comment
+        };
+
+        assert_eq!(merge(CommentPolicy::Drop), "");
+        assert_eq!(merge(CommentPolicy::KeepFirst), "base\n");
+        assert_eq!(
+            merge(CommentPolicy::Concat("---\n".into())),
+            "base\n---\noverlay\n"
+        );
+
+        let subset = base()
+            .with_comment("subset of base")
+            .subset(|f| f.name() != Path::new("a"), &CommentPolicy::KeepFirst);
+        assert_eq!(subset.to_string(), "subset of base\n-- b --\n");
+        let subset = base().subset(|_| true, &CommentPolicy::Drop);
+        assert_eq!(subset.to_string(), "-- a --\n-- b --\n");
+    }
+}
@@ -0,0 +1,364 @@
+/*!
+Streaming conversion between txtar and tar.
+
+The conversions work one entry at a time, so archives much larger than
+memory can be converted in a pipeline. Converting to tar holds one file
+in memory, as tar headers record the size before the data; converting
+from tar holds none.
+
+Only regular files are converted. Directories, links and other entries
+of tar archives are skipped, and the comment of txtar archives is
+dropped.
+
+# Examples
+
+```rust
+use txtar::convert;
+
+let txt = "-- a.txt --\nhello\n-- dir/b.txt --\nworld\n";
+let mut tar = Vec::new();
+convert::txtar_to_tar(txt.as_bytes(), &mut tar).unwrap();
+
+let mut back = Vec::new();
+convert::tar_to_txtar(tar.as_slice(), &mut back).unwrap();
+assert_eq!(String::from_utf8(back).unwrap(), txt);
+```
+!*/
+
+use std::{
+    io::{self, BufRead, Read, Write},
+    str,
+};
+
+use clean_path::Clean;
+
+use crate::{marker_name, Profile};
+
+const BLOCK: usize = 512;
+const CHUNK: usize = 8192;
+/// The largest pax header that is read into memory.
+const MAX_PAX: u64 = 1 << 20;
+
+/// Converts a tar archive read from `reader` into txtar written to
+/// `writer`, entry by entry. Long names in GNU and pax headers are
+/// supported.
+///
+/// # Errors
+///
+/// This function will error if the tar archive is malformed, or if a
+/// file name or the contents of a file are not valid UTF-8.
+/// Additionally, any errors caused by the underlying I/O operations will
+/// be propagated.
+pub fn tar_to_txtar<R: Read, W: Write>(mut reader: R, writer: &mut W) -> io::Result<()> {
+    let mut long_name: Option<String> = None;
+    let mut header = [0; BLOCK];
+    loop {
+        if !read_block(&mut reader, &mut header)? || header.iter().all(|&b| b == 0) {
+            return Ok(());
+        }
+        let size = octal(&header[124..136])?;
+        let mut data = (&mut reader).take(size);
+        match header[156] {
+            b'0' | b'\0' | b'7' => {
+                let name = match long_name.take() {
+                    Some(name) => name,
+                    None => header_name(&header)?,
+                };
+                write_file(&name, &mut data, writer)?;
+            }
+            b'L' => {
+                let mut name = Vec::new();
+                data.by_ref().take(MAX_PAX).read_to_end(&mut name)?;
+                let name = name.split(|&b| b == 0).next().unwrap_or_default();
+                long_name = Some(utf8(name.to_vec())?);
+            }
+            b'x' => {
+                let mut records = Vec::new();
+                data.by_ref().take(MAX_PAX).read_to_end(&mut records)?;
+                if let Some(path) = pax_path(&utf8(records)?) {
+                    long_name = Some(path.to_owned());
+                }
+            }
+            // A long name only applies to the entry right after it.
+            _ => long_name = None,
+        }
+        io::copy(&mut data, &mut io::sink())?;
+        if data.limit() > 0 {
+            return Err(invalid("unexpected end of tar archive"));
+        }
+        skip(&mut reader, padding(size))?;
+    }
+}
+
+/// Converts txtar read from `reader` into a tar archive written to
+/// `writer`, file by file. All entries are owned by root and have a
+/// modification time of zero, so the output only depends on the input.
+///
+/// # Errors
+///
+/// This function will error if the text is not valid UTF-8, if a file
+/// name would escape the archive root, or if a name or file is too large
+/// for a tar header. Additionally, any errors caused by the underlying
+/// I/O operations will be propagated.
+pub fn txtar_to_tar<R: BufRead, W: Write>(mut reader: R, writer: &mut W) -> io::Result<()> {
+    let mut file: Option<(String, Vec<u8>)> = None;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let text = str::from_utf8(&line).map_err(|e| invalid(&e.to_string()))?;
+        let marker = text.strip_suffix('\n').unwrap_or(text);
+        if let Some(name) = marker_name(marker, Profile::Lenient) {
+            if let Some((name, data)) = file.take() {
+                write_entry(writer, &name, data)?;
+            }
+            file = Some((name.to_owned(), Vec::new()));
+        } else if let Some((_, data)) = &mut file {
+            data.extend_from_slice(&line);
+        }
+    }
+    if let Some((name, data)) = file {
+        write_entry(writer, &name, data)?;
+    }
+
+    writer.write_all(&[0; 2 * BLOCK])
+}
+
+fn write_file<R: Read, W: Write>(name: &str, data: &mut R, writer: &mut W) -> io::Result<()> {
+    if name.contains('\n') || marker_name(&format!("-- {name} --"), Profile::Lenient) != Some(name)
+    {
+        return Err(invalid(&format!("{name}: not usable as a file name")));
+    }
+    writeln!(writer, "-- {name} --")?;
+
+    let mut buf = vec![0; CHUNK];
+    let mut carry = 0;
+    let mut last = b'\n';
+    loop {
+        let n = data.read(&mut buf[carry..])?;
+        if n == 0 {
+            break;
+        }
+        let len = carry + n;
+        let valid = match str::from_utf8(&buf[..len]) {
+            Ok(_) => len,
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => return Err(invalid(&format!("{name}: contents are not valid UTF-8"))),
+        };
+        writer.write_all(&buf[..valid])?;
+        if valid > 0 {
+            last = buf[valid - 1];
+        }
+        buf.copy_within(valid..len, 0);
+        carry = len - valid;
+    }
+    if carry > 0 {
+        return Err(invalid(&format!("{name}: contents are not valid UTF-8")));
+    }
+    if last != b'\n' {
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
+fn write_entry<W: Write>(writer: &mut W, name: &str, mut data: Vec<u8>) -> io::Result<()> {
+    if !data.is_empty() && !data.ends_with(b"\n") {
+        data.push(b'\n');
+    }
+    let path = std::path::Path::new(name).clean();
+    if path.starts_with("../") || path.is_absolute() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{name}: outside parent directory"),
+        ));
+    }
+    let name = path.to_string_lossy();
+
+    let mut header = [0; BLOCK];
+    let (prefix, name) = split_name(&name)?;
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    if data.len() as u64 >= 1 << 33 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{name}: file too large for tar header"),
+        ));
+    }
+    header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+    header[136..148].copy_from_slice(b"00000000000\0");
+    header[148..156].copy_from_slice(b"        ");
+    header[156] = b'0';
+    header[257..265].copy_from_slice(b"ustar\x0000");
+    let checksum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    writer.write_all(&header)?;
+    writer.write_all(&data)?;
+    writer.write_all(&[0; BLOCK][..padding(data.len() as u64) as usize])
+}
+
+/// Splits a name into the prefix and name fields of a ustar header.
+fn split_name(name: &str) -> io::Result<(&str, &str)> {
+    if name.len() <= 100 {
+        return Ok(("", name));
+    }
+    name.match_indices('/')
+        .map(|(i, _)| (&name[..i], &name[i + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100 && !name.is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{name}: name too long for tar header"),
+            )
+        })
+}
+
+fn header_name(header: &[u8; BLOCK]) -> io::Result<String> {
+    let field = |bytes: &[u8]| bytes.split(|&b| b == 0).next().unwrap_or_default().to_vec();
+    let name = utf8(field(&header[..100]))?;
+    if &header[257..262] != b"ustar" {
+        return Ok(name);
+    }
+    let prefix = utf8(field(&header[345..500]))?;
+    Ok(if prefix.is_empty() {
+        name
+    } else {
+        format!("{prefix}/{name}")
+    })
+}
+
+/// Returns the `path` record of a pax extended header.
+fn pax_path(records: &str) -> Option<&str> {
+    let mut rest = records;
+    let mut path = None;
+    while !rest.is_empty() {
+        let (len, _) = rest.split_once(' ')?;
+        let record = rest.get(..len.parse().ok()?)?;
+        rest = &rest[record.len()..];
+        let (_, key_value) = record.split_once(' ')?;
+        let (key, value) = key_value.strip_suffix('\n')?.split_once('=')?;
+        if key == "path" {
+            path = Some(value);
+        }
+    }
+    path
+}
+
+fn octal(field: &[u8]) -> io::Result<u64> {
+    let digits = str::from_utf8(field).map_err(|_| invalid("invalid size in tar header"))?;
+    let digits = digits.trim_matches(|c| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).map_err(|_| invalid("invalid size in tar header"))
+}
+
+fn padding(size: u64) -> u64 {
+    (BLOCK as u64 - size % BLOCK as u64) % BLOCK as u64
+}
+
+/// Reads one block, returning `false` at the end of the input.
+fn read_block<R: Read>(reader: &mut R, block: &mut [u8; BLOCK]) -> io::Result<bool> {
+    match reader.read_exact(block) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn skip<R: Read>(reader: &mut R, n: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(n), &mut io::sink())?;
+    if skipped < n {
+        return Err(invalid("unexpected end of tar archive"));
+    }
+    Ok(())
+}
+
+fn utf8(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|_| invalid("file name is not valid UTF-8"))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    fn to_tar(txt: &str) -> io::Result<Vec<u8>> {
+        let mut tar = Vec::new();
+        txtar_to_tar(txt.as_bytes(), &mut tar).map(|()| tar)
+    }
+
+    fn to_txtar(tar: &[u8]) -> io::Result<String> {
+        let mut txt = Vec::new();
+        tar_to_txtar(tar, &mut txt).map(|()| String::from_utf8(txt).unwrap())
+    }
+
+    #[test]
+    fn convert_roundtrip() {
+        let long = format!("{}/{}", "d".repeat(120), "f".repeat(90));
+        let big = "é".repeat(CHUNK);
+        let txt = format!("comment\n-- a --\nno newline\n-- ./b/../c --\n-- {long} --\n{big}\n");
+
+        let tar = to_tar(&txt).unwrap();
+        assert_eq!(tar.len() % BLOCK, 0);
+        assert_eq!(&tar[..2], b"a\0");
+        assert_eq!(&tar[257..263], b"ustar\0");
+        assert_eq!(
+            to_txtar(&tar).unwrap(),
+            format!("-- a --\nno newline\n-- c --\n-- {long} --\n{big}\n")
+        );
+
+        let err = to_tar("-- ../x --\n").unwrap_err();
+        assert_eq!(err.to_string(), "../x: outside parent directory");
+        let err = to_tar(&format!("-- {} --\n", "x".repeat(101))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn convert_tar_entries() {
+        let entry = |name: &str, kind: u8, data: &[u8]| {
+            let mut block = to_tar(&format!("-- {name} --\n")).unwrap();
+            block.truncate(BLOCK);
+            block[156] = kind;
+            block[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+            block.extend_from_slice(data);
+            block.resize(block.len() + padding(data.len() as u64) as usize, 0);
+            block
+        };
+
+        let pax = b"28 path=from/pax/header.txt\n";
+        let tar = [
+            entry("dir", b'5', b""),
+            entry("ignored", b'L', b"from/gnu.txt\0"),
+            entry("short", b'0', b"gnu\n"),
+            entry("ignored", b'x', pax),
+            entry("short", b'0', b"pax"),
+            entry("ignored", b'L', b"long/dir\0"),
+            entry("dir", b'5', b""),
+            entry("file", b'0', b"short"),
+            entry("link", b'2', b""),
+        ]
+        .concat();
+        assert_eq!(
+            to_txtar(&tar).unwrap(),
+            "-- from/gnu.txt --\ngnu\n-- from/pax/header.txt --\npax\n-- file --\nshort\n"
+        );
+
+        let truncated = &tar[..tar.len() - BLOCK];
+        assert!(to_txtar(truncated).is_ok());
+        let err = to_txtar(&entry("short", b'0', b"ab")[..BLOCK + 1]).unwrap_err();
+        assert_eq!(err.to_string(), "unexpected end of tar archive");
+        let err = to_txtar(&entry("bin", b'0', b"\xff")).unwrap_err();
+        assert_eq!(err.to_string(), "bin: contents are not valid UTF-8");
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    path::Path,
+};
+
+use clean_path::Clean;
+
+use crate::{Archive, File};
+
+const MAGIC: &str = "070701";
+const TRAILER: &str = "TRAILER!!!";
+const MODE_DIR: u32 = 0o040755;
+const MODE_FILE: u32 = 0o100644;
+const TYPE_FILE: u32 = 0o100000;
+
+impl Archive {
+    /// Serialize the archive as a `newc` format cpio archive into the I/O
+    /// stream, as used for Linux initramfs images.
+    ///
+    /// Parent directories are emitted before the files they contain. All
+    /// entries are owned by root and have a modification time of zero,
+    /// so the output only depends on the archive contents. Files use the
+    /// octal permissions of their `mode` metadata if set, and `0644`
+    /// otherwise.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if a file name would escape the archive
+    /// root. Additionally, any errors caused by the underlying I/O
+    /// operations will be propagated.
+    pub fn to_cpio_newc<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut w = CpioWriter { writer, ino: 0 };
+        let mut dirs = BTreeSet::new();
+
+        for file in &self.files {
+            let name = file.name.clean();
+            if name.starts_with("../") || name.is_absolute() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{}: outside parent directory", name.display()),
+                ));
+            }
+
+            let parents: Vec<&Path> = name.ancestors().skip(1).collect();
+            for dir in parents.into_iter().rev() {
+                if !dir.as_os_str().is_empty() && dirs.insert(dir.to_owned()) {
+                    w.entry(dir, MODE_DIR, 2, b"")?;
+                }
+            }
+            w.entry(&name, file_mode(file), 1, file.data.as_bytes())?;
+        }
+
+        w.entry(Path::new(TRAILER), 0, 1, b"")
+    }
+}
+
+struct CpioWriter<'a, W> {
+    writer: &'a mut W,
+    ino: u32,
+}
+
+impl<W: Write> CpioWriter<'_, W> {
+    fn entry(&mut self, name: &Path, mode: u32, nlink: u32, data: &[u8]) -> io::Result<()> {
+        let name = name.to_string_lossy();
+        // The trailer is the only entry without a mode and uses inode 0.
+        let ino = if mode == 0 {
+            0
+        } else {
+            self.ino += 1;
+            self.ino
+        };
+
+        let fields = [
+            ino,
+            mode,
+            0, // uid
+            0, // gid
+            nlink,
+            0, // mtime
+            len_u32(data.len())?,
+            0, // devmajor
+            0, // devminor
+            0, // rdevmajor
+            0, // rdevminor
+            len_u32(name.len() + 1)?,
+            0, // check
+        ];
+
+        write!(self.writer, "{MAGIC}")?;
+        for field in fields {
+            write!(self.writer, "{field:08X}")?;
+        }
+        self.writer.write_all(name.as_bytes())?;
+        self.writer.write_all(b"\0")?;
+        self.pad(MAGIC.len() + fields.len() * 8 + name.len() + 1)?;
+        self.writer.write_all(data)?;
+        self.pad(data.len())
+    }
+
+    fn pad(&mut self, len: usize) -> io::Result<()> {
+        let padding = (4 - len % 4) % 4;
+        self.writer.write_all(&[0; 3][..padding])
+    }
+}
+
+fn file_mode(file: &File) -> u32 {
+    file.metadata()
+        .get("mode")
+        .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+        .map_or(MODE_FILE, |mode| TYPE_FILE | (mode & 0o7777))
+}
+
+fn len_u32(len: usize) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "entry too large for cpio archive",
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseOptions;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn cpio_newc() {
+        let txt = "-- init # mode=0755 --\n#!/bin/sh\n-- etc/hosts # mode=rw --\nlocalhost\n";
+        let options = ParseOptions::new().annotations(true);
+        let archive = Archive::parse_with(txt, &options).unwrap();
+        let mut out = Vec::new();
+        archive.to_cpio_newc(&mut out).unwrap();
+
+        let mut entries = Vec::new();
+        let mut rest = &out[..];
+        loop {
+            let header = std::str::from_utf8(&rest[..110]).unwrap();
+            assert_eq!(&header[..6], MAGIC);
+            let field =
+                |i: usize| usize::from_str_radix(&header[6 + i * 8..14 + i * 8], 16).unwrap();
+            let (mode, size, namesize) = (field(1), field(6), field(11));
+
+            let name = std::str::from_utf8(&rest[110..110 + namesize - 1]).unwrap();
+            let data_start = (110 + namesize).next_multiple_of(4);
+            let data = std::str::from_utf8(&rest[data_start..data_start + size]).unwrap();
+            entries.push((name.to_owned(), format!("{mode:o}"), data.to_owned()));
+
+            rest = &rest[(data_start + size).next_multiple_of(4)..];
+            if name == TRAILER {
+                break;
+            }
+        }
+
+        assert!(rest.is_empty());
+        assert_eq!(
+            entries,
+            [
+                ("init".into(), "100755".into(), "#!/bin/sh\n".into()),
+                ("etc".into(), "40755".into(), "".into()),
+                ("etc/hosts".into(), "100644".into(), "localhost\n".into()),
+                (TRAILER.into(), "0".into(), "".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::{collections::HashMap, mem};
+
+use crate::{Archive, DuplicateError};
+
+/// How [`Archive::dedup`] resolves files that share a name.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DedupPolicy {
+    /// Keep the data of the first file with each name.
+    KeepFirst,
+    /// Keep the data of the last file with each name.
+    KeepLast,
+    /// Fail with a [`DuplicateError`] on the first repeated name.
+    Error,
+    /// Concatenate the data of all files with the same name, in order.
+    Concat,
+}
+
+impl Archive {
+    /// Merges files with the same name according to the policy, so every
+    /// name occurs at most once. Each remaining file takes the position
+    /// of the first file with its name.
+    ///
+    /// # Errors
+    ///
+    /// With [`DedupPolicy::Error`], this function errors on the first
+    /// repeated name and leaves the archive unchanged.
+    #[doc(alias = "dedup_names")]
+    pub fn dedup(&mut self, policy: DedupPolicy) -> Result<(), DuplicateError> {
+        if policy == DedupPolicy::Error {
+            let mut seen = HashMap::new();
+            for file in &self.files {
+                if let Some(first) = seen.insert(&file.name, file) {
+                    return Err(DuplicateError::new(first, file));
+                }
+            }
+            return Ok(());
+        }
+
+        let mut index = HashMap::new();
+        let mut files = Vec::with_capacity(self.files.len());
+        for file in mem::take(&mut self.files) {
+            let Some(&i) = index.get(&file.name) else {
+                index.insert(file.name.clone(), files.len());
+                files.push(file);
+                continue;
+            };
+            match policy {
+                DedupPolicy::KeepFirst | DedupPolicy::Error => {}
+                DedupPolicy::KeepLast => files[i] = file,
+                DedupPolicy::Concat => {
+                    files[i].data.push_str(&file.data);
+                    files[i].added_newline = file.added_newline;
+                }
+            }
+        }
+        self.files = files;
+        self.reindex();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn dedup_policies() {
+        let txt = "-- a --\n1\n-- b --\n2\n-- a --\n3\n-- a --\n4\n";
+        let check = |policy, expected: &str| {
+            let mut archive = Archive::from(txt);
+            archive.dedup(policy).unwrap();
+            assert_eq!(archive.to_string(), expected, "dedup[{policy:?}]");
+        };
+
+        check(DedupPolicy::KeepFirst, "-- a --\n1\n-- b --\n2\n");
+        check(DedupPolicy::KeepLast, "-- a --\n4\n-- b --\n2\n");
+        check(DedupPolicy::Concat, "-- a --\n1\n3\n4\n-- b --\n2\n");
+
+        let mut archive = Archive::from(txt);
+        let err = archive.dedup(DedupPolicy::Error).unwrap_err();
+        assert_eq!(err.to_string(), "a: duplicate file name");
+        assert_eq!(archive, Archive::from(txt));
+
+        archive.set_origin("a.txtar");
+        let err = archive.dedup(DedupPolicy::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "a: duplicate file name (from a.txtar and a.txtar)"
+        );
+
+        let mut archive = Archive::from("-- a --\n-- b --\n");
+        assert!(archive.dedup(DedupPolicy::Error).is_ok());
+    }
+}
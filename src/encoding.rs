@@ -0,0 +1,167 @@
+/*!
+Detection and transcoding of text that is not UTF-8.
+
+Fixtures harvested from other platforms are frequently encoded as UTF-16
+or Latin-1. The helpers in this module recognize those encodings and
+convert such data to UTF-8 so it can be stored in an archive.
+
+Any sequence of bytes is valid Latin-1, so binary data would pass for
+it. Latin-1 is therefore only assumed by the `_or_latin1` functions.
+
+This module requires the `encoding` feature.
+
+# Examples
+
+```rust
+use txtar::encoding::{self, Encoding};
+
+let utf16 = b"\xff\xfeh\0i\0";
+assert_eq!(encoding::detect(utf16), Some(Encoding::Utf16Le));
+assert_eq!(encoding::to_utf8(utf16).unwrap(), "hi");
+
+assert!(encoding::to_utf8(b"caf\xe9").is_err());
+assert_eq!(encoding::to_utf8_or_latin1(b"caf\xe9").unwrap(), "café");
+```
+!*/
+
+use thiserror::Error;
+
+/// A text encoding recognized by [`detect`] or [`detect_or_latin1`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+#[derive(Error, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DecodeError {
+    #[error("data does not look like text")]
+    Binary,
+    #[error("invalid {0:?} data")]
+    Invalid(Encoding),
+}
+
+/// Guesses the encoding of the given data.
+///
+/// Byte order marks are honored. Without one, valid UTF-8 is preferred,
+/// then UTF-16 if every other byte is NUL. Data that fits neither is
+/// considered binary and yields `None`.
+pub fn detect(bytes: &[u8]) -> Option<Encoding> {
+    match bytes {
+        [0xef, 0xbb, 0xbf, ..] => return Some(Encoding::Utf8),
+        [0xff, 0xfe, ..] => return Some(Encoding::Utf16Le),
+        [0xfe, 0xff, ..] => return Some(Encoding::Utf16Be),
+        _ => {}
+    }
+
+    if let Ok(s) = std::str::from_utf8(bytes) {
+        if !s.contains('\0') {
+            return Some(Encoding::Utf8);
+        }
+    }
+
+    if !bytes.is_empty() && bytes.len().is_multiple_of(2) {
+        let nul_at = |parity| bytes.iter().skip(parity).step_by(2).all(|&b| b == 0);
+        if nul_at(1) {
+            return Some(Encoding::Utf16Le);
+        }
+        if nul_at(0) {
+            return Some(Encoding::Utf16Be);
+        }
+    }
+
+    None
+}
+
+/// Guesses the encoding of the given data like [`detect`], falling back
+/// to Latin-1 for data without NUL bytes or other control characters.
+pub fn detect_or_latin1(bytes: &[u8]) -> Option<Encoding> {
+    if let Some(encoding) = detect(bytes) {
+        return Some(encoding);
+    }
+
+    let is_control = |b: &u8| b.is_ascii_control() && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c);
+    if bytes.iter().any(is_control) {
+        return None;
+    }
+
+    Some(Encoding::Latin1)
+}
+
+/// Decodes data in the given encoding to UTF-8, removing a leading byte
+/// order mark if present.
+pub fn decode(bytes: &[u8], encoding: Encoding) -> Result<String, DecodeError> {
+    match encoding {
+        Encoding::Utf8 => {
+            let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+            String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::Invalid(encoding))
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if !bytes.len().is_multiple_of(2) {
+                return Err(DecodeError::Invalid(encoding));
+            }
+            let units = bytes.chunks_exact(2).map(|pair| {
+                let pair = [pair[0], pair[1]];
+                match encoding {
+                    Encoding::Utf16Le => u16::from_le_bytes(pair),
+                    _ => u16::from_be_bytes(pair),
+                }
+            });
+            let s = char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map_err(|_| DecodeError::Invalid(encoding))?;
+            Ok(s.strip_prefix('\u{feff}').map(str::to_owned).unwrap_or(s))
+        }
+        Encoding::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+    }
+}
+
+/// Detects the encoding of the given data with [`detect`] and decodes it
+/// to UTF-8.
+pub fn to_utf8(bytes: &[u8]) -> Result<String, DecodeError> {
+    let encoding = detect(bytes).ok_or(DecodeError::Binary)?;
+    decode(bytes, encoding)
+}
+
+/// Detects the encoding of the given data with [`detect_or_latin1`] and
+/// decodes it to UTF-8.
+pub fn to_utf8_or_latin1(bytes: &[u8]) -> Result<String, DecodeError> {
+    let encoding = detect_or_latin1(bytes).ok_or(DecodeError::Binary)?;
+    decode(bytes, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn detect_and_decode() {
+        check(b"plain", Some(Encoding::Utf8), Ok("plain"));
+        check(b"\xef\xbb\xbfbom", Some(Encoding::Utf8), Ok("bom"));
+        check(b"\xff\xfea\0b\0", Some(Encoding::Utf16Le), Ok("ab"));
+        check(b"\xfe\xff\0a\0b", Some(Encoding::Utf16Be), Ok("ab"));
+        check(b"a\0b\0", Some(Encoding::Utf16Le), Ok("ab"));
+        check(b"\0a\0b", Some(Encoding::Utf16Be), Ok("ab"));
+        check(b"na\xefve\r\n", None, Err(DecodeError::Binary));
+        check(b"\x7fELF\x02\x01\x01\0", None, Err(DecodeError::Binary));
+
+        assert_eq!(detect_or_latin1(b"a\0b\0"), Some(Encoding::Utf16Le));
+        assert_eq!(detect_or_latin1(b"na\xefve\r\n"), Some(Encoding::Latin1));
+        assert_eq!(to_utf8_or_latin1(b"na\xefve\r\n").unwrap(), "naïve\r\n");
+        assert_eq!(detect_or_latin1(b"\x7fELF\x02\x01\x01\0"), None);
+        check(
+            b"\xff\xfe\x00\xd8",
+            Some(Encoding::Utf16Le),
+            Err(DecodeError::Invalid(Encoding::Utf16Le)),
+        );
+    }
+
+    fn check(bytes: &[u8], encoding: Option<Encoding>, decoded: Result<&str, DecodeError>) {
+        assert_eq!(detect(bytes), encoding, "detect[{bytes:?}]");
+        let decoded = decoded.map(str::to_owned);
+        assert_eq!(to_utf8(bytes), decoded, "to_utf8[{bytes:?}]");
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{Archive, File};
+
+/// How random the data of a file looks, as reported by
+/// [`Archive::entropy_report`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entropy<'a> {
+    pub file: &'a File,
+    /// The Shannon entropy of the bytes of the data, from 0 to 8 bits per
+    /// byte. Source code and prose typically score 4 to 5, base64 about
+    /// 6, and compressed or encrypted data close to 8.
+    pub bits_per_byte: f64,
+}
+
+impl Entropy<'_> {
+    /// The entropy above which data is flagged as suspicious. It lies
+    /// between typical text and base64.
+    pub const SUSPICIOUS: f64 = 5.2;
+
+    /// Returns an estimate of the size of the data after compression
+    /// relative to its size, from 0 to 1. It only accounts for the
+    /// frequencies of bytes, so repetitive text compresses better than
+    /// estimated.
+    pub fn compression_ratio(&self) -> f64 {
+        self.bits_per_byte / 8.0
+    }
+
+    /// Returns `true` if the data looks like an encoded binary or an
+    /// embedded archive rather than text.
+    pub fn is_suspicious(&self) -> bool {
+        self.bits_per_byte > Entropy::SUSPICIOUS
+    }
+}
+
+impl File {
+    /// Returns the Shannon entropy of the bytes of the data, in bits per
+    /// byte. See [`Entropy::bits_per_byte`].
+    pub fn entropy(&self) -> f64 {
+        let mut counts = [0usize; 256];
+        for &b in self.data.as_bytes() {
+            counts[usize::from(b)] += 1;
+        }
+
+        let len = self.data.len() as f64;
+        counts
+            .iter()
+            .filter(|&&n| n > 0)
+            .map(|&n| {
+                let p = n as f64 / len;
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+impl Archive {
+    /// Returns the entropy of every file, in order, to flag accidentally
+    /// committed binaries or embedded archives.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use txtar::Archive;
+    ///
+    /// let archive = Archive::from("\
+    /// -- main.rs --
+    /// fn main() { println!(\"hello, world\"); }
+    /// -- blob.b64 --
+    /// H4sIAAAAAAAAA+3OMQqAMAxA0T1HyQ2aVmMvU9CpQ8Fq8fwKDuIiOBTR/Zb/Fr4EYwJ9zV3nRoSkTQgZtSxNMaU4GnEJYs6ZqrHLAwA=
+    /// ");
+    /// let flagged: Vec<_> = archive
+    ///     .entropy_report()
+    ///     .into_iter()
+    ///     .filter(|e| e.is_suspicious())
+    ///     .map(|e| e.file.name().display().to_string())
+    ///     .collect();
+    /// assert_eq!(flagged, ["blob.b64"]);
+    /// ```
+    pub fn entropy_report(&self) -> Vec<Entropy<'_>> {
+        self.files
+            .iter()
+            .map(|file| Entropy {
+                file,
+                bits_per_byte: file.entropy(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn entropy() {
+        let mut ascii: String = (0..128u8).map(char::from).filter(|&c| c != '\n').collect();
+        ascii.push('\n');
+        let all_ascii = File::new("ascii", &ascii);
+        assert_eq!(all_ascii.entropy(), 7.0);
+        assert_eq!(File::new("empty", "").entropy(), 0.0);
+        assert_eq!(File::new("ab", "abb\n").entropy(), 1.5);
+
+        let archive = Archive::from("-- same --\naaaaaaa\n");
+        let report = archive.entropy_report();
+        assert!(!report[0].is_suspicious());
+        assert!(report[0].compression_ratio() < 0.1);
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+
+use crate::{Archive, File};
+
+/// A view into the first file with some name in an archive, which may
+/// not exist.
+///
+/// This enum is created by [`Archive::entry`].
+#[derive(Debug)]
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+/// A view into an existing file. Part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a> {
+    archive: &'a mut Archive,
+    position: usize,
+}
+
+/// A view into a missing file. Part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct VacantEntry<'a> {
+    archive: &'a mut Archive,
+    name: PathBuf,
+}
+
+impl Archive {
+    /// Returns the entry for the first file with the given name, for
+    /// in-place inspection, modification or insertion.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use txtar::Archive;
+    ///
+    /// let mut archive = Archive::from("-- config.toml --\nx = 1\n");
+    /// archive
+    ///     .entry("config.toml")
+    ///     .and_modify(|f| f.set_data(&f.data().replace('1', "2")))
+    ///     .or_insert("x = 0");
+    /// archive.entry("log").or_insert_with(String::new);
+    ///
+    /// assert_eq!(archive.to_string(), "-- config.toml --\nx = 2\n-- log --\n");
+    /// ```
+    pub fn entry<P: AsRef<Path>>(&mut self, name: P) -> Entry<'_> {
+        let name = name.as_ref();
+        match self.position(name) {
+            Some(position) => Entry::Occupied(OccupiedEntry {
+                archive: self,
+                position,
+            }),
+            None => Entry::Vacant(VacantEntry {
+                archive: self,
+                name: name.to_owned(),
+            }),
+        }
+    }
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the name of the file of this entry.
+    pub fn name(&self) -> &Path {
+        match self {
+            Entry::Occupied(e) => e.get().name(),
+            Entry::Vacant(e) => e.name(),
+        }
+    }
+
+    /// Returns the file, appending one with the given data if it is
+    /// missing.
+    pub fn or_insert(self, data: &str) -> &'a mut File {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(data),
+        }
+    }
+
+    /// Returns the file, appending one with the data returned by the
+    /// function if it is missing.
+    pub fn or_insert_with<F: FnOnce() -> String>(self, data: F) -> &'a mut File {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(&data()),
+        }
+    }
+
+    /// Calls the function on the file if it exists.
+    pub fn and_modify<F: FnOnce(&mut File)>(mut self, f: F) -> Entry<'a> {
+        if let Entry::Occupied(e) = &mut self {
+            f(e.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns the file.
+    pub fn get(&self) -> &File {
+        &self.archive.files[self.position]
+    }
+
+    /// Returns the file mutably.
+    pub fn get_mut(&mut self) -> &mut File {
+        &mut self.archive.files[self.position]
+    }
+
+    /// Returns the file mutably, with the lifetime of the archive.
+    pub fn into_mut(self) -> &'a mut File {
+        &mut self.archive.files[self.position]
+    }
+
+    /// Replaces the data of the file, returning the old data.
+    pub fn insert(&mut self, data: &str) -> String {
+        let file = self.get_mut();
+        let old = std::mem::take(&mut file.data);
+        file.set_data(data);
+        old
+    }
+
+    /// Removes the file from the archive and returns it.
+    pub fn remove(self) -> File {
+        let file = self.archive.files.remove(self.position);
+        self.archive.reindex();
+        file
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    /// Returns the name of the missing file.
+    pub fn name(&self) -> &Path {
+        &self.name
+    }
+
+    /// Appends a file with the entry's name and the given data, and
+    /// returns it.
+    pub fn insert(self, data: &str) -> &'a mut File {
+        self.archive.push(File::new(self.name, data));
+        self.archive.files.last_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn entries() {
+        let mut archive = Archive::from("-- a --\n1\n-- b --\n2\n-- a --\n3\n");
+        archive.build_index();
+
+        match archive.entry("a") {
+            Entry::Occupied(mut e) => assert_eq!(e.insert("one"), "1\n"),
+            Entry::Vacant(_) => panic!("expected an occupied entry"),
+        }
+        match archive.entry("b") {
+            Entry::Occupied(e) => assert_eq!(e.remove().data(), "2\n"),
+            Entry::Vacant(_) => panic!("expected an occupied entry"),
+        }
+        let entry = archive.entry("c");
+        assert_eq!(entry.name(), Path::new("c"));
+        entry.or_insert("new").set_data("c");
+        archive.entry("c").or_insert_with(|| unreachable!());
+
+        assert_eq!(
+            archive.to_string(),
+            "-- a --\none\n-- a --\n3\n-- c --\nc\n"
+        );
+        assert_eq!(archive.get("c").map(File::data), Some("c\n"));
+    }
+}
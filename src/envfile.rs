@@ -0,0 +1,139 @@
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+use crate::{Archive, Manifest, MaterializeError, MaterializeOptions};
+
+/// The name of the member holding environment variables.
+const ENV_FILE: &str = ".env";
+
+impl Archive {
+    /// Writes the archive to the directory at the given path like
+    /// [`Archive::materialize_with`], expanding placeholders in the files, and
+    /// sets the variables of its `.env` member on the command.
+    ///
+    /// The `.env` member holds `KEY=value` lines. Blank lines and lines
+    /// starting with `#` are skipped, and values may be quoted. A placeholder
+    /// `${KEY}` in any file is replaced by the value of the variable, and
+    /// `${WORK}` by the path of the directory. Other placeholders are left
+    /// as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```rust no_run
+    /// use std::process::Command;
+    /// use txtar::{Archive, MaterializeOptions};
+    ///
+    /// let archive = Archive::from("\
+    /// -- .env --
+    /// PORT=8080
+    /// -- config.toml --
+    /// port = ${PORT}
+    /// data = \"${WORK}/data\"
+    /// ");
+    ///
+    /// let mut server = Command::new("my-server");
+    /// server.arg("config.toml").current_dir("/tmp/case");
+    /// archive
+    ///     .materialize_with_env("/tmp/case", &mut server, &MaterializeOptions::new())
+    ///     .unwrap();
+    /// let status = server.status().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will error in the same cases as
+    /// [`Archive::materialize_with`].
+    pub fn materialize_with_env<P: AsRef<Path>>(
+        &self,
+        path: P,
+        command: &mut Command,
+        options: &MaterializeOptions,
+    ) -> Result<Manifest, MaterializeError> {
+        let path = path.as_ref();
+        let mut vars = self
+            .get(ENV_FILE)
+            .map_or_else(BTreeMap::new, |f| parse_env(&f.data));
+        command.envs(&vars);
+        vars.insert("WORK", path.to_string_lossy().into_owned());
+
+        let mut archive = self.clone();
+        for file in &mut archive {
+            if file.data.contains("${") {
+                file.data = expand(&file.data, &vars);
+            }
+        }
+
+        archive.materialize_with(path, options)
+    }
+}
+
+fn parse_env(s: &str) -> BTreeMap<&str, String> {
+    s.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| l.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let unquoted = ['"', '\'']
+                .into_iter()
+                .find_map(|q| value.strip_prefix(q)?.strip_suffix(q))
+                .unwrap_or(value);
+            (key.trim(), unquoted.to_owned())
+        })
+        .collect()
+}
+
+fn expand(s: &str, vars: &BTreeMap<&str, String>) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest
+            .find('}')
+            .and_then(|end| Some((end, vars.get(&rest[2..end])?)));
+        match value {
+            Some((end, value)) => {
+                out.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push_str("${");
+                rest = &rest[2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::{prelude::*, TempDir};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn materialize_with_env() {
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::from(
+            "-- .env --\n# ports\nPORT = 8080\nNAME=\"my app\"\n\n-- run.sh --\n${NAME} on ${PORT} in ${WORK} ${HOME} ${\n",
+        );
+        let mut command = Command::new("true");
+
+        archive
+            .materialize_with_env(&dir, &mut command, &MaterializeOptions::new())
+            .unwrap();
+
+        let envs: Vec<_> = command.get_envs().collect();
+        assert_eq!(
+            envs,
+            [
+                ("NAME".as_ref(), Some("my app".as_ref())),
+                ("PORT".as_ref(), Some("8080".as_ref()))
+            ]
+        );
+        let run = format!("my app on 8080 in {} ${{HOME}} ${{\n", dir.path().display());
+        dir.child("run.sh").assert(run);
+        dir.child(".env").assert(predicates::path::exists());
+    }
+}
@@ -1,11 +1,135 @@
-use std::io;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
 
 use thiserror::Error;
 
+use crate::{spans::Span, File};
+
 #[derive(Error, Debug)]
 pub enum MaterializeError {
     #[error("{0}")]
     Io(#[from] io::Error),
     #[error("{0}: outside parent directory")]
     DirEscape(String),
+    #[error("{0}: no target directory for file")]
+    Unmapped(String),
+    #[error("{0}: {1}")]
+    Rejected(String, &'static str),
+    #[error("{0}: conflicts with {1} on case-insensitive filesystems")]
+    CaseCollision(String, String),
+    #[error("{0}: target directory is not writable: {1}")]
+    NotWritable(String, #[source] io::Error),
+    #[error("{0}: outside the Cargo workspace at {1}")]
+    OutsideWorkspace(String, String),
+}
+
+#[derive(Error, Debug)]
+pub enum PackError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{}: binary file", .0.display())]
+    Binary(PathBuf),
+    #[error("{}: special file", .0.display())]
+    SpecialFile(PathBuf),
+}
+
+#[derive(Error, Debug)]
+pub enum MultipartError {
+    #[error("malformed multipart body: {0}")]
+    Malformed(&'static str),
+    #[error("{0}: not valid UTF-8")]
+    Utf8(String),
+    #[error("{0}: outside parent directory")]
+    DirEscape(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ParseError {
+    #[error("{name}: cannot include {include}: {error}")]
+    Resolve {
+        name: String,
+        include: String,
+        #[source]
+        error: io::Error,
+    },
+    #[error("{0}: include directive followed by data")]
+    IncludeWithData(String),
+    #[error("line {line}: {reason}")]
+    Strict { line: usize, reason: &'static str },
+    #[error("{0}: {1}")]
+    InvalidName(String, &'static str),
 }
+
+/// Two files with the same name, as found by [`Archive::dedup`] and
+/// [`Archive::merge`](crate::Archive::merge). The message names the
+/// [origins](File::origin) of the files when they are known.
+///
+/// [`Archive::dedup`]: crate::Archive::dedup
+#[derive(Error, Debug)]
+#[error("{}: duplicate file name{}", .name.display(), origins(.first.as_deref(), .second.as_deref()))]
+pub struct DuplicateError {
+    pub name: PathBuf,
+    /// The origin of the file that came first.
+    pub first: Option<PathBuf>,
+    /// The origin of the file that repeated the name.
+    pub second: Option<PathBuf>,
+}
+
+impl DuplicateError {
+    pub(crate) fn new(first: &File, second: &File) -> DuplicateError {
+        DuplicateError {
+            name: second.name.clone(),
+            first: first.origin.clone(),
+            second: second.origin.clone(),
+        }
+    }
+}
+
+fn origins(first: Option<&Path>, second: Option<&Path>) -> String {
+    if first.is_none() && second.is_none() {
+        return String::new();
+    }
+    let origin = |p: Option<&Path>| p.map_or("unknown".into(), |p| p.display().to_string());
+    format!(" (from {} and {})", origin(first), origin(second))
+}
+
+/// The first difference between txtar text and its reserialization,
+/// as reported by [`check_roundtrip`](crate::check_roundtrip).
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+#[error("line {line}: {expected:?} is reserialized as {actual:?}")]
+pub struct RoundtripIssue {
+    /// The line number of the first differing line, starting at 1.
+    pub line: usize,
+    /// The byte offset of the first differing byte, which is the same in
+    /// the input and the reserialized text. It is the length of the
+    /// shorter text if one is a prefix of the other.
+    pub offset: usize,
+    /// The location of the differing line in the input.
+    pub input: Span,
+    /// The location of the differing line in the reserialized text.
+    pub output: Span,
+    /// The differing line of the input, including its newline.
+    pub expected: String,
+    /// The differing line of the reserialized text.
+    pub actual: String,
+}
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+#[error("line {line}: {reason}")]
+pub struct JsonlError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+#[error("{}: {reason}", .name.display())]
+pub struct ExpectationError {
+    pub name: PathBuf,
+    pub reason: &'static str,
+}
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+#[error("{}: file name is not valid UTF-8", .0.display())]
+pub struct NonUtf8Name(pub PathBuf);
@@ -0,0 +1,94 @@
+use std::{fmt::Write as _, path::PathBuf};
+
+use crate::jsonl::push_string;
+
+/// Something that happened while materializing an archive, recorded when
+/// [`MaterializeOptions::event_log`] is set.
+///
+/// Paths are relative to the directory the files were written to.
+/// Existing files are never overwritten, as materializing fails instead.
+///
+/// [`MaterializeOptions::event_log`]: crate::MaterializeOptions::event_log
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Event {
+    /// A directory was created.
+    DirCreated(PathBuf),
+    /// A file was written. The hash is the 64-bit FNV-1a hash of the data
+    /// as 16 hexadecimal digits, as in [`Archive::content_hash`].
+    ///
+    /// [`Archive::content_hash`]: crate::Archive::content_hash
+    FileWritten {
+        path: PathBuf,
+        size: usize,
+        hash: String,
+    },
+    /// A file was skipped because the condition in its `if` metadata did
+    /// not hold.
+    Skipped(PathBuf),
+}
+
+impl Event {
+    /// Returns the event as a single-line JSON object with an `event` key
+    /// of `dir`, `file` or `skipped`.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"event\":");
+        let path = match self {
+            Event::DirCreated(path) => {
+                out.push_str("\"dir\"");
+                path
+            }
+            Event::FileWritten { path, .. } => {
+                out.push_str("\"file\"");
+                path
+            }
+            Event::Skipped(path) => {
+                out.push_str("\"skipped\"");
+                path
+            }
+        };
+        out.push_str(",\"path\":");
+        push_string(&mut out, &path.to_string_lossy());
+        if let Event::FileWritten { size, hash, .. } = self {
+            write!(out, ",\"size\":{size},\"hash\":").unwrap();
+            push_string(&mut out, hash);
+        }
+        out.push('}');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Archive, MaterializeOptions};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn event_log() {
+        let mut archive = Archive::from("-- a/b --\nhi\n-- c --\n-- d --\n");
+        let conditions = [(1, "!linux,linux"), (2, "nowhere")];
+        for (i, condition) in conditions {
+            archive.files[i]
+                .metadata_mut()
+                .insert("if".into(), condition.into());
+        }
+        let dir = assert_fs::TempDir::new().unwrap();
+        let options = MaterializeOptions::new().event_log(true);
+        let manifest = archive.materialize_with(dir.path(), &options).unwrap();
+
+        let mut out = Vec::new();
+        manifest.events_to_jsonl(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"event\":\"dir\",\"path\":\"a\"}\n\
+             {\"event\":\"file\",\"path\":\"a/b\",\"size\":3,\"hash\":\"33734a193006ba70\"}\n\
+             {\"event\":\"file\",\"path\":\"c\",\"size\":0,\"hash\":\"cbf29ce484222325\"}\n\
+             {\"event\":\"skipped\",\"path\":\"d\"}\n"
+        );
+
+        let dir = assert_fs::TempDir::new().unwrap();
+        let manifest = archive
+            .materialize_with(dir.path(), &MaterializeOptions::new())
+            .unwrap();
+        assert!(manifest.events().is_empty());
+    }
+}
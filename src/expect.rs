@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use crate::{Archive, ExpectationError, File};
+
+/// How expected output is compared with actual output.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Pattern {
+    /// The output must equal the text.
+    Literal(String),
+    /// The output must match the regular expression.
+    Regex(String),
+}
+
+/**
+The expected result of running a process, as described by the `stdout`,
+`stderr` and `exit` members of an archive.
+
+The `stdout` and `stderr` members hold the expected output, compared
+literally unless their [metadata](File::metadata) sets `match=regex`.
+The `exit` member holds the expected exit code. Missing members are not
+checked, and other members are ignored.
+
+# Examples
+
+```rust
+use txtar::{Archive, Expectation, ParseOptions, Pattern};
+
+let txt = "\
+-- input.txt --
+hello
+-- stdout --
+HELLO
+-- stderr # match=regex --
+^warning: .*$
+-- exit --
+0
+";
+let archive = Archive::parse_with(txt, &ParseOptions::new().annotations(true)).unwrap();
+let expectation = Expectation::from_archive(&archive).unwrap();
+
+assert_eq!(expectation.stdout, Some(Pattern::Literal("HELLO\n".into())));
+assert_eq!(expectation.stderr, Some(Pattern::Regex("^warning: .*$\n".into())));
+assert_eq!(expectation.exit, Some(0));
+```
+**/
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Expectation {
+    pub stdout: Option<Pattern>,
+    pub stderr: Option<Pattern>,
+    pub exit: Option<i32>,
+}
+
+impl Expectation {
+    /// The metadata key that selects how output is compared, with a
+    /// value of `literal` or `regex`.
+    pub const METADATA_KEY: &'static str = "match";
+
+    /// Reads the expectation from the members of the archive.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if a member occurs more than once, the
+    /// exit code is not an integer or the comparison mode is unknown.
+    pub fn from_archive(archive: &Archive) -> Result<Expectation, ExpectationError> {
+        Ok(Expectation {
+            stdout: member(archive, "stdout")?.map(pattern).transpose()?,
+            stderr: member(archive, "stderr")?.map(pattern).transpose()?,
+            exit: member(archive, "exit")?.map(exit_code).transpose()?,
+        })
+    }
+}
+
+fn member<'a>(
+    archive: &'a Archive,
+    name: &'static str,
+) -> Result<Option<&'a File>, ExpectationError> {
+    let mut files = archive.get_all(Path::new(name));
+    let file = files.next();
+    match files.next() {
+        Some(file) => Err(error(file, "duplicate member")),
+        None => Ok(file),
+    }
+}
+
+fn pattern(file: &File) -> Result<Pattern, ExpectationError> {
+    let data = file.data.clone();
+    match file
+        .metadata
+        .get(Expectation::METADATA_KEY)
+        .map(String::as_str)
+    {
+        None | Some("literal") => Ok(Pattern::Literal(data)),
+        Some("regex") => Ok(Pattern::Regex(data)),
+        Some(_) => Err(error(file, "unknown match mode")),
+    }
+}
+
+fn exit_code(file: &File) -> Result<i32, ExpectationError> {
+    file.data
+        .trim()
+        .parse()
+        .map_err(|_| error(file, "exit code is not an integer"))
+}
+
+fn error(file: &File, reason: &'static str) -> ExpectationError {
+    ExpectationError {
+        name: file.name.clone(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn expectations() {
+        let archive = Archive::from("-- stdout --\nok\n-- exit --\n 2\n");
+        let expectation = Expectation::from_archive(&archive).unwrap();
+        assert_eq!(
+            expectation,
+            Expectation {
+                stdout: Some(Pattern::Literal("ok\n".into())),
+                stderr: None,
+                exit: Some(2),
+            }
+        );
+        let empty = Expectation::from_archive(&Archive::new()).unwrap();
+        assert_eq!(empty, Expectation::default());
+
+        let mut glob = Archive::from("-- stderr --\n*\n");
+        glob.files[0]
+            .metadata_mut()
+            .insert("match".into(), "glob".into());
+        for (archive, err) in [
+            (
+                Archive::from("-- exit --\nzero\n"),
+                "exit: exit code is not an integer",
+            ),
+            (
+                Archive::from("-- stdout --\n-- stdout --\n"),
+                "stdout: duplicate member",
+            ),
+            (glob, "stderr: unknown match mode"),
+        ] {
+            let actual = Expectation::from_archive(&archive).unwrap_err();
+            assert_eq!(actual.to_string(), err);
+        }
+    }
+}
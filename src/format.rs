@@ -0,0 +1,342 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Write as _},
+    io::{self, Write},
+};
+
+use crate::{parse::ANNOTATIONS, Archive, File, Ordering, Profile};
+
+/// What to do with files whose data did not end with a newline when they
+/// were created or parsed.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum NewlinePolicy {
+    /// Write a final newline, as the txtar format requires.
+    #[default]
+    Always,
+    /// Leave out the final newline that was added, if the file is written
+    /// last. Other files always end with a newline, or their last line
+    /// would swallow the next file marker.
+    Preserve,
+    /// Fail to write the archive if any file lacked a final newline. The
+    /// `Display` implementation of [`Formatted`] writes a final newline
+    /// instead, as with [`NewlinePolicy::Always`].
+    Error,
+}
+
+/// Options controlling how an archive is serialized as txtar.
+#[derive(Debug, Default, Clone)]
+pub struct FormatOptions {
+    ordering: Ordering,
+    profile: Profile,
+    newline: NewlinePolicy,
+    skip_duplicates: bool,
+}
+
+impl FormatOptions {
+    pub fn new() -> FormatOptions {
+        FormatOptions::default()
+    }
+
+    /// Sets the order in which files are written. The archive itself is
+    /// not reordered.
+    pub fn ordering(mut self, ordering: Ordering) -> FormatOptions {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Sets the profile to format for. With [`Profile::GoCompat`], file
+    /// metadata is left out.
+    pub fn profile(mut self, profile: Profile) -> FormatOptions {
+        self.profile = profile;
+        self
+    }
+
+    /// Sets how files that lacked a final newline are written.
+    pub fn newline(mut self, newline: NewlinePolicy) -> FormatOptions {
+        self.newline = newline;
+        self
+    }
+
+    /// Sets whether only the first file with each name is written, in
+    /// line with [`Archive::get`]. By default, all files are written. See
+    /// [`Archive::dedup`] for other ways to resolve duplicates.
+    pub fn skip_duplicates(mut self, skip_duplicates: bool) -> FormatOptions {
+        self.skip_duplicates = skip_duplicates;
+        self
+    }
+}
+
+/// An archive paired with [`FormatOptions`], which implements `Display`
+/// according to the options.
+///
+/// Formatting never fails: with [`NewlinePolicy::Error`], files that
+/// lacked a final newline are written as with [`NewlinePolicy::Always`].
+/// Use [`Formatted::check_newlines`] or [`Archive::to_writer_with`] to get
+/// an error instead.
+///
+/// This struct is created by [`Archive::display_with`].
+#[derive(Debug)]
+pub struct Formatted<'a> {
+    archive: &'a Archive,
+    options: &'a FormatOptions,
+}
+
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let files = self.files();
+        write!(f, "{}", self.archive.comment)?;
+
+        let last = files.len().wrapping_sub(1);
+        for (i, file) in files.into_iter().enumerate() {
+            write_marker(f, file, self.options.profile != Profile::GoCompat)?;
+
+            let preserve = self.options.newline == NewlinePolicy::Preserve && i == last;
+            let data = match file.data.strip_suffix('\n') {
+                Some(data) if preserve && file.added_newline => data,
+                _ => &file.data,
+            };
+            write!(f, "{data}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders a single file as its `-- name --` marker line followed by its
+/// data, as it appears in an archive.
+///
+/// # Examples
+///
+/// ```rust
+/// use txtar::File;
+///
+/// let file = File::new("a.txt", "hello");
+/// assert_eq!(file.to_string(), "-- a.txt --\nhello\n");
+/// ```
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_marker(f, self, true)?;
+        write!(f, "{}", self.data)
+    }
+}
+
+fn write_marker(f: &mut fmt::Formatter<'_>, file: &File, metadata: bool) -> fmt::Result {
+    write!(f, "-- {}", file.name.display())?;
+    if metadata {
+        for (i, (key, value)) in file.metadata.iter().enumerate() {
+            let sep = if i == 0 { ANNOTATIONS } else { " " };
+            write!(f, "{sep}{key}={value}")?;
+        }
+    }
+    writeln!(f, " --")
+}
+
+impl Formatted<'_> {
+    /// Returns the files that are written, in the order they are written.
+    fn files(&self) -> Vec<&File> {
+        let mut files: Vec<&File> = self.archive.files.iter().collect();
+        if self.options.skip_duplicates {
+            let mut seen = HashSet::new();
+            files.retain(|f| seen.insert(&f.name));
+        }
+        let ordering = self.options.ordering;
+        if ordering != Ordering::Original {
+            files.sort_by(|a, b| ordering.compare(&a.name, &b.name));
+        }
+        files
+    }
+
+    /// Checks that no written file lacked a final newline, if the
+    /// options use [`NewlinePolicy::Error`].
+    ///
+    /// # Errors
+    ///
+    /// This function will error with [`io::ErrorKind::InvalidData`] naming
+    /// the first written file that lacked a final newline.
+    pub fn check_newlines(&self) -> io::Result<()> {
+        if self.options.newline != NewlinePolicy::Error {
+            return Ok(());
+        }
+        match self.files().into_iter().find(|f| f.added_newline) {
+            Some(file) => {
+                let msg = format!("{}: missing final newline", file.name.display());
+                Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Archive {
+    /// Returns a value that formats the archive as txtar according to the
+    /// given options.
+    pub fn display_with<'a>(&'a self, options: &'a FormatOptions) -> Formatted<'a> {
+        Formatted {
+            archive: self,
+            options,
+        }
+    }
+
+    /// Serialize the archive as txtar into the I/O stream according to the
+    /// given options.
+    pub fn to_writer_with<W: Write>(
+        &self,
+        writer: &mut W,
+        options: &FormatOptions,
+    ) -> io::Result<()> {
+        let formatted = self.display_with(options);
+        formatted.check_newlines()?;
+        write!(writer, "{formatted}")
+    }
+
+    /// Feeds the txtar serialization of the archive to `update` in pieces,
+    /// without building it in memory, so it can be hashed for a cache key.
+    ///
+    /// `update` can be the `update` method of a cryptographic digest, whose
+    /// result is then the same as hashing the whole text at once. Feeding
+    /// a [`Hasher`](std::hash::Hasher) is only stable for hashers that
+    /// treat their input as a stream of bytes, and the standard library's
+    /// hashers may change between Rust versions, so use
+    /// [`Archive::content_hash`] for a key that is stored.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use txtar::Archive;
+    ///
+    /// let archive = Archive::from("-- a --\nhello\n");
+    /// let mut text = Vec::new();
+    /// archive.hash_serialized(|bytes| text.extend_from_slice(bytes));
+    /// assert_eq!(text, archive.to_string().as_bytes());
+    /// ```
+    pub fn hash_serialized<F: FnMut(&[u8])>(&self, update: F) {
+        struct Update<F>(F);
+
+        impl<F: FnMut(&[u8])> fmt::Write for Update<F> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                (self.0)(s.as_bytes());
+                Ok(())
+            }
+        }
+
+        write!(Update(update), "{self}").expect("hashing cannot fail");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn format_ordering() {
+        let archive = Archive::from("comment\n-- b --\n-- a/x --\n-- a --\n");
+        let options = FormatOptions::new().ordering(Ordering::DirectoriesFirst);
+
+        let mut out = Vec::new();
+        archive.to_writer_with(&mut out, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "comment\n-- a/x --\n-- a --\n-- b --\n"
+        );
+        assert_eq!(
+            archive.display_with(&FormatOptions::new()).to_string(),
+            archive.to_string()
+        );
+    }
+
+    #[test]
+    fn format_newline() {
+        let archive = Archive::from("-- a --\nx\n-- b --\ny");
+        let format = |newline| {
+            let mut out = Vec::new();
+            let options = FormatOptions::new().newline(newline);
+            archive
+                .to_writer_with(&mut out, &options)
+                .map(|()| String::from_utf8(out).unwrap())
+                .map_err(|e| e.to_string())
+        };
+
+        assert_eq!(
+            format(NewlinePolicy::Always).unwrap(),
+            "-- a --\nx\n-- b --\ny\n"
+        );
+        assert_eq!(
+            format(NewlinePolicy::Preserve).unwrap(),
+            "-- a --\nx\n-- b --\ny"
+        );
+        assert_eq!(
+            format(NewlinePolicy::Error).unwrap_err(),
+            "b: missing final newline"
+        );
+        let options = FormatOptions::new().newline(NewlinePolicy::Error);
+        let formatted = archive.display_with(&options);
+        assert!(formatted.check_newlines().is_err());
+        assert_eq!(formatted.to_string(), "-- a --\nx\n-- b --\ny\n");
+        let options = FormatOptions::new().newline(NewlinePolicy::Preserve);
+        assert!(archive.display_with(&options).check_newlines().is_ok());
+
+        let reversed = FormatOptions::new()
+            .newline(NewlinePolicy::Preserve)
+            .ordering(Ordering::Lexicographic);
+        let archive = Archive::from("-- b --\ny\n-- a --\nx");
+        assert_eq!(
+            archive.display_with(&reversed).to_string(),
+            "-- a --\nx\n-- b --\ny\n"
+        );
+    }
+
+    #[test]
+    fn format_skip_duplicates() {
+        let archive = Archive::from("-- log --\n1\n-- a --\n-- log --\n2\n");
+        let logs: Vec<_> = archive.get_all("log").map(File::data).collect();
+        assert_eq!(logs, ["1\n", "2\n"]);
+        assert_eq!(archive.get_all("b").count(), 0);
+
+        let options = FormatOptions::new().skip_duplicates(true);
+        assert_eq!(
+            archive.display_with(&options).to_string(),
+            "-- log --\n1\n-- a --\n"
+        );
+
+        let archive = Archive::from("-- log --\n1\n-- log --\n2");
+        let options = options.newline(NewlinePolicy::Error);
+        let mut out = Vec::new();
+        archive.to_writer_with(&mut out, &options).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "-- log --\n1\n");
+        assert!(archive.display_with(&options).check_newlines().is_ok());
+        assert_eq!(archive.display_with(&options).to_string(), "-- log --\n1\n");
+    }
+
+    #[test]
+    fn display_file() {
+        let text = "-- a --\n-- b # mode=0755 --\n#!/bin/sh\n";
+        let archive = Archive::from(text);
+        let files: String = archive.iter().map(File::to_string).collect();
+        assert_eq!(files, text);
+        assert_eq!(
+            File::new("c", "no newline").to_string(),
+            "-- c --\nno newline\n"
+        );
+    }
+
+    #[test]
+    fn hash_serialized() {
+        use std::hash::{DefaultHasher, Hasher};
+
+        let archive = Archive::from("comment\n-- a --\nhello\n-- b/c --\nworld");
+        let mut text = Vec::new();
+        archive.hash_serialized(|bytes| text.extend_from_slice(bytes));
+        assert_eq!(text, archive.to_string().as_bytes());
+
+        let hash = |archive: &Archive| {
+            let mut hasher = DefaultHasher::new();
+            archive.hash_serialized(|bytes| hasher.write(bytes));
+            hasher.finish()
+        };
+        let mut hasher = DefaultHasher::new();
+        hasher.write(archive.to_string().as_bytes());
+        assert_eq!(hash(&archive), hasher.finish());
+        assert_ne!(hash(&archive), hash(&Archive::from("-- a --\nhello\n")));
+    }
+}
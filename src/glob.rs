@@ -0,0 +1,65 @@
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::Archive;
+
+/// Compiles a glob matched against archive file names, where `*` and `?`
+/// do not match `/` and `**` matches across directories.
+pub(crate) fn matcher(glob: &str) -> Result<GlobMatcher, globset::Error> {
+    let glob = GlobBuilder::new(glob).literal_separator(true).build()?;
+    Ok(glob.compile_matcher())
+}
+
+impl Archive {
+    /// Returns a copy of the archive with only the files whose names
+    /// match the glob. `*` and `?` do not match `/`, while `**` matches
+    /// across directories.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use txtar::Archive;
+    ///
+    /// let archive = Archive::from("-- src/a.rs --\n-- src/b/c.rs --\n-- README --\n");
+    /// let sources = archive.matching("src/**/*.rs").unwrap();
+    /// assert_eq!(sources.to_string(), "-- src/a.rs --\n-- src/b/c.rs --\n");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the glob is invalid.
+    pub fn matching(&self, glob: &str) -> Result<Archive, globset::Error> {
+        let matcher = matcher(glob)?;
+        let files = self
+            .files
+            .iter()
+            .filter(|f| matcher.is_match(&f.name))
+            .cloned()
+            .collect();
+        Ok(Archive::from_parts(&self.comment, files))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn matching() {
+        let archive = Archive::from("c\n-- a.rs --\n-- x/a.rs --\n-- x/a.rsx --\n-- x/y/b.rs --\n");
+        let names = |glob| {
+            let matched = archive.matching(glob).unwrap();
+            assert_eq!(matched.comment(), "c\n");
+            matched
+                .iter()
+                .map(|f| f.name().display().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("*.rs"), ["a.rs"]);
+        assert_eq!(names("x/*"), ["x/a.rs", "x/a.rsx"]);
+        assert_eq!(names("**/*.rs"), ["a.rs", "x/a.rs", "x/y/b.rs"]);
+        assert!(names("*.go").is_empty());
+        assert!(archive.matching("a[").is_err());
+    }
+}
@@ -0,0 +1,227 @@
+/*!
+Updating golden test cases.
+
+A golden test case is an archive holding the input files of a test and
+the output expected from running the code under test on them. When the
+expected output changes, an [`Updater`] reruns the command for each case
+and rewrites the expected files with what it produced.
+
+# Examples
+
+```rust no_run
+use std::process::Command;
+use txtar::golden::Updater;
+
+let updated = Updater::new(|| {
+    let mut command = Command::new("cargo");
+    command.args(["run", "--", "input.txt", "-o", "output.txt"]);
+    command
+})
+.output("output.txt")
+.run("tests/cases")
+.unwrap();
+println!("updated {} cases", updated.len());
+```
+
+With the default prefixes, a case looks like this:
+
+```text
+-- input/input.txt --
+hello
+-- expected/output.txt --
+HELLO
+```
+!*/
+
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{Archive, MaterializeError};
+
+type MakeCommand<'a> = dyn Fn() -> Command + 'a;
+
+/// A builder for updating the expected output of golden test cases.
+pub struct Updater<'a> {
+    command: Box<MakeCommand<'a>>,
+    outputs: Vec<PathBuf>,
+    input: PathBuf,
+    expected: PathBuf,
+}
+
+impl<'a> Updater<'a> {
+    /// Creates an updater that runs the commands returned by the given
+    /// function. They are run in a temporary directory holding the
+    /// inputs of a case, and their exit status is not checked.
+    pub fn new<F>(command: F) -> Updater<'a>
+    where
+        F: Fn() -> Command + 'a,
+    {
+        Updater {
+            command: Box::new(command),
+            outputs: Vec::new(),
+            input: PathBuf::from("input"),
+            expected: PathBuf::from("expected"),
+        }
+    }
+
+    /// Adds a path, relative to the directory the command runs in, whose
+    /// contents are recorded as expected output.
+    pub fn output<P: AsRef<Path>>(mut self, path: P) -> Updater<'a> {
+        self.outputs.push(path.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the prefix of the input files in a case. Defaults to `input`.
+    pub fn input_prefix<P: AsRef<Path>>(mut self, prefix: P) -> Updater<'a> {
+        self.input = prefix.as_ref().to_owned();
+        self
+    }
+
+    /// Sets the prefix of the expected files in a case. Defaults to
+    /// `expected`.
+    pub fn expected_prefix<P: AsRef<Path>>(mut self, prefix: P) -> Updater<'a> {
+        self.expected = prefix.as_ref().to_owned();
+        self
+    }
+
+    /// Updates every `.txtar` file in the directory at the given path,
+    /// returning the paths of the files that changed.
+    ///
+    /// # Errors
+    ///
+    /// This function stops at the first error, such as a case that
+    /// cannot be read or materialized or a command that cannot be
+    /// started. Cases updated before that have already been written.
+    pub fn run<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<PathBuf>> {
+        let mut cases = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "txtar") && path.is_file() {
+                cases.push(path);
+            }
+        }
+        cases.sort();
+
+        let mut updated = Vec::new();
+        for case in cases {
+            let txt = fs::read_to_string(&case)?;
+            let mut archive = Archive::from(txt.as_str());
+            self.update(&mut archive)?;
+            let new = archive.to_string();
+            if new != txt {
+                fs::write(&case, new)?;
+                updated.push(case);
+            }
+        }
+
+        Ok(updated)
+    }
+
+    /// Runs the command on the inputs of the case and replaces its
+    /// expected files with the outputs. Expected files whose output was
+    /// not created are removed.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the inputs cannot be materialized, the
+    /// command cannot be started or an output cannot be read.
+    pub fn update(&self, archive: &mut Archive) -> io::Result<()> {
+        let dir = temp_dir()?;
+        let result = self.run_case(archive, &dir);
+        let cleanup = fs::remove_dir_all(&dir);
+        let outputs = result?;
+        cleanup?;
+
+        for (output, data) in self.outputs.iter().zip(outputs) {
+            let name = self.expected.join(output);
+            match data {
+                Some(data) => archive.entry(name).or_insert("").set_data(&data),
+                None => archive.retain(|f| f.name != name),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_case(&self, archive: &Archive, dir: &Path) -> io::Result<Vec<Option<String>>> {
+        let inputs: Archive = archive
+            .iter()
+            .filter_map(|f| {
+                let name = f.name.strip_prefix(&self.input).ok()?;
+                Some((name, f.data.as_str()))
+            })
+            .collect();
+        inputs.materialize(dir).map_err(|e| match e {
+            MaterializeError::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        })?;
+
+        (self.command)().current_dir(dir).output()?;
+
+        self.outputs
+            .iter()
+            .map(|output| match fs::read_to_string(dir.join(output)) {
+                Ok(data) => Ok(Some(data)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for Updater<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Updater")
+            .field("outputs", &self.outputs)
+            .field("input", &self.input)
+            .field("expected", &self.expected)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Creates a fresh directory for running a case.
+fn temp_dir() -> io::Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let dir = env::temp_dir().join(format!("txtar-golden-{}-{n}", process::id()));
+    fs::create_dir(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use assert_fs::{prelude::*, TempDir};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn update_golden() {
+        let dir = TempDir::new().unwrap();
+        let case = "-- input/in --\nhello\n-- expected/out --\nstale\n-- expected/err --\nold\n";
+        dir.child("a.txtar").write_str(case).unwrap();
+        dir.child("b.txtar")
+            .write_str("-- input/in --\nx\n-- expected/out --\nX\n")
+            .unwrap();
+        dir.child("notes.txt").write_str("ignored").unwrap();
+
+        let updater = Updater::new(|| {
+            let mut command = Command::new("sh");
+            command.args(["-c", "tr a-z A-Z < in > out"]);
+            command
+        })
+        .output("out")
+        .output("err");
+        let updated = updater.run(&dir).unwrap();
+
+        assert_eq!(updated, [dir.child("a.txtar").to_path_buf()]);
+        dir.child("a.txtar")
+            .assert("-- input/in --\nhello\n-- expected/out --\nHELLO\n");
+        dir.child("b.txtar")
+            .assert("-- input/in --\nx\n-- expected/out --\nX\n");
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use crate::{Archive, File};
+
+/// An optional map from file names to positions in an archive.
+///
+/// It takes no part in comparisons, so archives with and without an
+/// index are equal.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Index(Option<HashMap<PathBuf, usize>>);
+
+impl PartialEq for Index {
+    fn eq(&self, _: &Index) -> bool {
+        true
+    }
+}
+
+impl Eq for Index {}
+
+impl Hash for Index {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl Index {
+    /// Records a file appended at `position`.
+    pub(crate) fn push(&mut self, name: &Path, position: usize) {
+        if let Some(index) = &mut self.0 {
+            if !index.contains_key(name) {
+                index.insert(name.to_owned(), position);
+            }
+        }
+    }
+}
+
+impl Archive {
+    /// Builds an index of the file names, making [`Archive::get`] a
+    /// constant-time lookup.
+    ///
+    /// The index is kept up to date by the methods that add, remove,
+    /// rename or reorder files, at the cost of rebuilding it on each such
+    /// change.
+    pub fn build_index(&mut self) {
+        let mut index = HashMap::with_capacity(self.files.len());
+        for (i, file) in self.files.iter().enumerate() {
+            if let Entry::Vacant(e) = index.entry(file.name.clone()) {
+                e.insert(i);
+            }
+        }
+        self.index = Index(Some(index));
+    }
+
+    /// Drops the index built by [`Archive::build_index`].
+    pub fn drop_index(&mut self) {
+        self.index = Index(None);
+    }
+
+    /// Returns `true` if the archive has an index.
+    pub fn is_indexed(&self) -> bool {
+        self.index.0.is_some()
+    }
+
+    /// Returns the first file with the given name.
+    ///
+    /// This scans the files in order unless the archive has an index.
+    pub fn get<P: AsRef<Path>>(&self, name: P) -> Option<&File> {
+        let i = self.position(name.as_ref())?;
+        Some(&self.files[i])
+    }
+
+    /// Returns a mutable reference to the first file with the given name.
+    pub fn get_mut<P: AsRef<Path>>(&mut self, name: P) -> Option<&mut File> {
+        let i = self.position(name.as_ref())?;
+        Some(&mut self.files[i])
+    }
+
+    /// Returns the first file whose name equals the given name ignoring
+    /// case, as it would be found on a case-insensitive filesystem.
+    ///
+    /// This always scans the files in order.
+    pub fn get_ci<P: AsRef<Path>>(&self, name: P) -> Option<&File> {
+        let name = name.as_ref().to_string_lossy().to_lowercase();
+        self.files
+            .iter()
+            .find(|f| f.name.to_string_lossy().to_lowercase() == name)
+    }
+
+    /// Returns `true` if the archive contains a file with the given name.
+    pub fn contains<P: AsRef<Path>>(&self, name: P) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns an iterator over all files with the given name, in order.
+    pub fn get_all<'a, P>(&'a self, name: P) -> impl Iterator<Item = &'a File> + 'a
+    where
+        P: AsRef<Path> + 'a,
+    {
+        self.files.iter().filter(move |f| f.name == name.as_ref())
+    }
+
+    /// Returns the position of the first file with the given name.
+    pub(crate) fn position(&self, name: &Path) -> Option<usize> {
+        match &self.index.0 {
+            Some(index) => index.get(name).copied(),
+            None => self.files.iter().position(|f| f.name == name),
+        }
+    }
+
+    /// Rebuilds the index, if there is one, after the files changed.
+    pub(crate) fn reindex(&mut self) {
+        if self.is_indexed() {
+            self.build_index();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ordering;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn index() {
+        let mut archive = Archive::from("-- b --\nb1\n-- a --\na\n-- b --\nb2\n");
+        archive.build_index();
+        assert!(archive.is_indexed());
+        assert_eq!(archive, Archive::from(archive.to_string().as_str()));
+        assert_eq!(archive.get("b").map(File::data), Some("b1\n"));
+        assert_eq!(archive.get("c"), None);
+
+        archive.sort(Ordering::Lexicographic);
+        archive += Archive::from("-- c --\nc\n");
+        let data = ["a", "b", "c"].map(|n| archive.get(n).unwrap().data());
+        assert_eq!(data, ["a\n", "b1\n", "c\n"]);
+
+        archive.drop_index();
+        assert_eq!(archive.get(Path::new("c")).map(File::data), Some("c\n"));
+    }
+
+    #[test]
+    fn lookup() {
+        for indexed in [false, true] {
+            let mut archive = Archive::from("-- go.mod --\nmodule x\n-- a --\n");
+            if indexed {
+                archive.build_index();
+            }
+
+            assert!(archive.contains("go.mod"));
+            assert!(!archive.contains("go.sum"));
+            let file = archive.get_mut("go.mod").unwrap();
+            file.metadata_mut().insert("k".into(), "v".into());
+            assert_eq!(archive.get("go.mod").unwrap().metadata()["k"], "v");
+            assert!(archive.get_mut("b").is_none());
+            assert_eq!(
+                archive.get_ci("GO.MOD").map(File::name),
+                Some(Path::new("go.mod"))
+            );
+            assert!(archive.get_ci("go.sum").is_none());
+        }
+    }
+}
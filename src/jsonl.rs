@@ -0,0 +1,340 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    str::Chars,
+};
+
+use crate::{Archive, File, JsonlError};
+
+impl Archive {
+    /// Writes the archive as JSON Lines: an optional
+    /// `{"comment": ...}` object followed by one
+    /// `{"name": ..., "data": ...}` object per file, with a `metadata`
+    /// object for files that have metadata.
+    pub fn to_jsonl<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut line = String::new();
+        if !self.comment.is_empty() {
+            line.push_str("{\"comment\":");
+            push_string(&mut line, &self.comment);
+            line.push_str("}\n");
+            writer.write_all(line.as_bytes())?;
+        }
+
+        for file in &self.files {
+            line.clear();
+            line.push_str("{\"name\":");
+            push_string(&mut line, &file.name.to_string_lossy());
+            line.push_str(",\"data\":");
+            push_string(&mut line, &file.data);
+            if !file.metadata.is_empty() {
+                line.push_str(",\"metadata\":{");
+                for (i, (key, value)) in file.metadata.iter().enumerate() {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    push_string(&mut line, key);
+                    line.push(':');
+                    push_string(&mut line, value);
+                }
+                line.push('}');
+            }
+            line.push_str("}\n");
+            writer.write_all(line.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads an archive from JSON Lines as written by
+    /// [`Archive::to_jsonl`]. Blank lines and unknown keys are ignored,
+    /// whatever the type of their values.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if a line is not a JSON object of the
+    /// expected shape.
+    pub fn from_jsonl(s: &str) -> Result<Archive, JsonlError> {
+        let mut comment = String::new();
+        let mut files = Vec::new();
+
+        for (i, line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = |reason| JsonlError {
+                line: i + 1,
+                reason,
+            };
+
+            let mut parser = Parser {
+                chars: line.chars(),
+            };
+            let Value::Object(entries) = parser.value(0).map_err(error)? else {
+                return Err(error("expected an object"));
+            };
+            parser.end().map_err(error)?;
+
+            let (mut name, mut data, mut metadata) = (None, None, None);
+            for (key, value) in entries {
+                match (key.as_str(), value) {
+                    ("comment", Value::String(c)) => comment.push_str(&c),
+                    ("name", Value::String(n)) => name = Some(n),
+                    ("data", Value::String(d)) => data = Some(d),
+                    ("metadata", Value::Object(m)) => metadata = Some(m),
+                    ("comment" | "name" | "data" | "metadata", _) => {
+                        return Err(error("unexpected value type"))
+                    }
+                    _ => {}
+                }
+            }
+
+            let Some(name) = name else {
+                if data.is_some() || metadata.is_some() {
+                    return Err(error("missing name"));
+                }
+                continue;
+            };
+            let mut file = File::new(name, data.as_deref().unwrap_or_default());
+            for (key, value) in metadata.unwrap_or_default() {
+                let Value::String(value) = value else {
+                    return Err(error("unexpected value type"));
+                };
+                file.metadata.insert(key, value);
+            }
+            files.push(file);
+        }
+
+        Ok(Archive::from_parts(&comment, files))
+    }
+}
+
+pub(crate) fn push_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// The deepest nesting of arrays and objects that is parsed, which keeps
+/// malicious input from overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+enum Value {
+    String(String),
+    Object(Vec<(String, Value)>),
+    /// A number, boolean, null or array, which the format never uses.
+    Other,
+}
+
+/// A JSON parser that keeps the strings and objects used by
+/// [`Archive::to_jsonl`] and only validates other values.
+struct Parser<'a> {
+    chars: Chars<'a>,
+}
+
+impl Parser<'_> {
+    /// Parses a value nested in `depth` arrays and objects.
+    fn value(&mut self, depth: usize) -> Result<Value, &'static str> {
+        let token = self.next_token();
+        if matches!(token, Some('{' | '[')) && depth >= MAX_DEPTH {
+            return Err("nesting too deep");
+        }
+        match token {
+            Some('"') => self.string().map(Value::String),
+            Some('{') => self.object(depth + 1).map(Value::Object),
+            Some('[') => self.array(depth + 1).map(|()| Value::Other),
+            Some('t') => self.literal("rue"),
+            Some('f') => self.literal("alse"),
+            Some('n') => self.literal("ull"),
+            Some(c @ ('-' | '0'..='9')) => self.number(c),
+            Some(_) => Err("invalid value"),
+            None => Err("unexpected end of line"),
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<(), &'static str> {
+        if self.peek_token() == Some(']') {
+            self.next_token();
+            return Ok(());
+        }
+        loop {
+            self.value(depth)?;
+            match self.next_token() {
+                Some(',') => {}
+                Some(']') => return Ok(()),
+                _ => return Err("expected `,` or `]`"),
+            }
+        }
+    }
+
+    fn literal(&mut self, rest: &str) -> Result<Value, &'static str> {
+        match self.chars.as_str().strip_prefix(rest) {
+            Some(after) => {
+                self.chars = after.chars();
+                Ok(Value::Other)
+            }
+            None => Err("invalid value"),
+        }
+    }
+
+    fn number(&mut self, first: char) -> Result<Value, &'static str> {
+        let rest = self.chars.as_str();
+        let len = rest
+            .find(|c: char| !matches!(c, '0'..='9' | '.' | 'e' | 'E' | '+' | '-'))
+            .unwrap_or(rest.len());
+        let number = format!("{first}{}", &rest[..len]);
+        number.parse::<f64>().map_err(|_| "invalid value")?;
+        self.chars = rest[len..].chars();
+        Ok(Value::Other)
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Vec<(String, Value)>, &'static str> {
+        let mut entries = Vec::new();
+        let mut token = self.next_token();
+        if token == Some('}') {
+            return Ok(entries);
+        }
+        loop {
+            if token != Some('"') {
+                return Err("expected a key");
+            }
+            let key = self.string()?;
+            if self.next_token() != Some(':') {
+                return Err("expected `:`");
+            }
+            entries.push((key, self.value(depth)?));
+            match self.next_token() {
+                Some(',') => token = self.next_token(),
+                Some('}') => return Ok(entries),
+                _ => return Err("expected `,` or `}`"),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        let mut s = String::new();
+        loop {
+            match self.chars.next().ok_or("unterminated string")? {
+                '"' => return Ok(s),
+                '\\' => {
+                    let c = match self.chars.next().ok_or("unterminated string")? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => self.unicode_escape()?,
+                        _ => return Err("invalid escape"),
+                    };
+                    s.push(c);
+                }
+                c if c < ' ' => return Err("control character in string"),
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, &'static str> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or("invalid escape");
+        }
+        if self.chars.next() != Some('\\') || self.chars.next() != Some('u') {
+            return Err("invalid escape");
+        }
+        let low = self.hex4()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err("invalid escape");
+        }
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).ok_or("invalid escape")
+    }
+
+    fn hex4(&mut self) -> Result<u32, &'static str> {
+        (0..4).try_fold(0, |n, _| {
+            let digit = self.chars.next().and_then(|c| c.to_digit(16));
+            Ok(n * 16 + digit.ok_or("invalid escape")?)
+        })
+    }
+
+    fn peek_token(&self) -> Option<char> {
+        self.chars.clone().find(|c| !c.is_ascii_whitespace())
+    }
+
+    fn next_token(&mut self) -> Option<char> {
+        self.chars.by_ref().find(|c| !c.is_ascii_whitespace())
+    }
+
+    fn end(&mut self) -> Result<(), &'static str> {
+        match self.next_token() {
+            None => Ok(()),
+            Some(_) => Err("trailing characters"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn jsonl_roundtrip() {
+        let mut archive = Archive::from("note \"1\"\n-- a\\b --\ntab\there\u{1}\n-- c/é --\n");
+        archive.files[1]
+            .metadata_mut()
+            .insert("mode".into(), "0644".into());
+
+        let mut out = Vec::new();
+        archive.to_jsonl(&mut out).unwrap();
+        let jsonl = String::from_utf8(out).unwrap();
+        assert_eq!(
+            jsonl,
+            concat!(
+                "{\"comment\":\"note \\\"1\\\"\\n\"}\n",
+                "{\"name\":\"a\\\\b\",\"data\":\"tab\\there\\u0001\\n\"}\n",
+                "{\"name\":\"c/é\",\"data\":\"\",\"metadata\":{\"mode\":\"0644\"}}\n",
+            )
+        );
+        assert_eq!(Archive::from_jsonl(&jsonl).unwrap(), archive);
+
+        let escaped = r#" { "name" : "\ud83d\ude00\u00e9", "x": {}, "data": "\/" } "#;
+        let archive = Archive::from_jsonl(escaped).unwrap();
+        assert_eq!(archive.to_string(), "-- 😀é --\n/\n");
+
+        let jq = r#"{"name":"a","data":"x","size":1,"n":[-1.5e3, true, null, {}, []],"ok":false}"#;
+        let archive = Archive::from_jsonl(jq).unwrap();
+        assert_eq!(archive.to_string(), "-- a --\nx\n");
+
+        for (bad, reason) in [
+            ("[]", "expected an object"),
+            ("{\"name\": 1}", "unexpected value type"),
+            ("{\"x\": tru}", "invalid value"),
+            ("{\"x\": [1,]}", "invalid value"),
+            (
+                &format!("{{\"x\": {}", "[".repeat(100_000)),
+                "nesting too deep",
+            ),
+            (&"{\"x\": ".repeat(100_000), "nesting too deep"),
+            ("{\"data\": \"x\"}", "missing name"),
+            ("{\"name\": {}}", "unexpected value type"),
+            ("{\"name\": \"a\"} x", "trailing characters"),
+            ("{\"name\": \"a\\ud800\"}", "invalid escape"),
+            ("{\"name\": \"a", "unterminated string"),
+        ] {
+            let err = Archive::from_jsonl(&format!("\n{bad}")).unwrap_err();
+            assert_eq!(err, JsonlError { line: 2, reason }, "from_jsonl[{bad}]");
+        }
+    }
+}
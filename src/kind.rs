@@ -0,0 +1,83 @@
+use std::fmt;
+
+use crate::File;
+
+/// Whether a file holds text, binary data or nothing at all.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum FileKind {
+    Text,
+    Binary,
+    Empty,
+}
+
+impl FileKind {
+    /// The metadata key that overrides the detected kind of a file, with
+    /// a value of `text`, `binary` or `empty`.
+    pub const METADATA_KEY: &'static str = "kind";
+
+    fn from_name(name: &str) -> Option<FileKind> {
+        match name {
+            "text" => Some(FileKind::Text),
+            "binary" => Some(FileKind::Binary),
+            "empty" => Some(FileKind::Empty),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FileKind::Text => "text",
+            FileKind::Binary => "binary",
+            FileKind::Empty => "empty",
+        };
+        // pad so that listings can align the kind column
+        f.pad(name)
+    }
+}
+
+impl File {
+    /// Classifies the file as text, binary or empty.
+    ///
+    /// A `kind` entry in the [metadata](File::metadata) takes precedence.
+    /// Otherwise, files containing NUL bytes or U+FFFD replacement
+    /// characters, which are left behind by lossy UTF-8 decoding, are
+    /// binary.
+    pub fn kind(&self) -> FileKind {
+        if let Some(kind) = self
+            .metadata
+            .get(FileKind::METADATA_KEY)
+            .and_then(|k| FileKind::from_name(k))
+        {
+            return kind;
+        }
+
+        if self.data.is_empty() {
+            FileKind::Empty
+        } else if self.data.contains(['\0', char::REPLACEMENT_CHARACTER]) {
+            FileKind::Binary
+        } else {
+            FileKind::Text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn file_kind() {
+        assert_eq!(File::new("a", "hello").kind(), FileKind::Text);
+        assert_eq!(File::new("a", "").kind(), FileKind::Empty);
+        assert_eq!(File::new("a", "\x7fELF\0\0").kind(), FileKind::Binary);
+        assert_eq!(File::new("a", "caf\u{fffd}").kind(), FileKind::Binary);
+
+        let mut file = File::new("a", "\0");
+        file.metadata_mut().insert("kind".into(), "text".into());
+        assert_eq!(file.kind(), FileKind::Text);
+        assert_eq!(format!("[{:<6}]", FileKind::Empty), "[empty ]");
+    }
+}
@@ -1,18 +1,99 @@
 #![doc=include_str!("../README.md")]
 
+mod archive_file;
+mod budget;
+pub mod build;
+mod builder;
+pub mod bundle;
+mod cache;
+mod comment;
+pub mod convert;
+mod cpio;
+mod dedup;
+#[cfg(feature = "encoding")]
+pub mod encoding;
+mod entropy;
+mod entry;
+mod envfile;
 mod error;
+mod events;
+mod expect;
+mod format;
+#[cfg(feature = "globset")]
+mod glob;
+pub mod golden;
+mod index;
+mod jsonl;
+mod kind;
+mod materialize;
+mod merge;
+mod meta;
+mod multipart;
+mod names;
+mod nest;
+mod order;
+mod pack;
+mod parse;
+mod profile;
+mod render;
+mod roundtrip;
+mod schema;
+#[cfg(feature = "secrets")]
+mod secrets;
+pub mod spans;
+mod stats;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod view;
+mod workspace;
 
 use std::{
-    fmt::Display,
-    fs,
-    io::{self, BufWriter, Write},
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display, Write as _},
+    hash::{Hash, Hasher},
+    io::{self, Read, Write},
+    mem,
+    ops::{self, Add, AddAssign},
     path::{Path, PathBuf},
-    str,
+    slice, str,
+    string::FromUtf8Error,
+    vec,
 };
 
-use clean_path::Clean;
-
-pub use error::MaterializeError;
+pub use archive_file::ArchiveFile;
+pub use budget::{Budget, BudgetViolation, Limited};
+pub use builder::ArchiveBuilder;
+pub use comment::{Comment, CommentMut, CommentPolicy};
+pub use dedup::DedupPolicy;
+pub use entropy::Entropy;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use error::{
+    DuplicateError, ExpectationError, JsonlError, MaterializeError, MultipartError, NonUtf8Name,
+    PackError, ParseError, RoundtripIssue,
+};
+pub use events::Event;
+pub use expect::{Expectation, Pattern};
+pub use format::{FormatOptions, Formatted, NewlinePolicy};
+pub use kind::FileKind;
+pub use materialize::{
+    cleanup, restore_writable, ContainedPaths, Manifest, MaterializeOptions, PathPolicy,
+};
+pub use merge::MergeStrategy;
+pub use meta::Meta;
+pub use names::NameRules;
+pub use order::Ordering;
+pub use pack::{
+    BinaryPolicy, PackOptions, PackReport, ReadErrorPolicy, SkipReason, Skipped, SpecialFilePolicy,
+};
+pub use parse::{DirResolver, ParseOptions, Resolver};
+pub use profile::Profile;
+pub use roundtrip::check_roundtrip;
+pub use schema::{Schema, Violation};
+#[cfg(feature = "secrets")]
+pub use secrets::{Finding, SecretScanner};
+pub use stats::DirStats;
+pub use view::View;
+pub use workspace::workspace_root;
 
 /**
 An archive represents a tree of text files.
@@ -40,38 +121,378 @@ let archive = Archive::from(txt);
 archive.materialize("/tmp/somedir/").unwrap();
 ```
 **/
-#[derive(Debug, Default, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Eq, PartialEq, Hash)]
 pub struct Archive {
     // internal invariant:
     // comment is fix_newlined
     comment: String,
     files: Vec<File>,
+    index: index::Index,
 }
 
-#[derive(Debug, Eq, PartialEq)]
-struct File {
+/// A file in an [`Archive`].
+///
+/// Files compare equal if their names, contents and metadata are equal,
+/// regardless of their origin and whether a final newline was added.
+#[derive(Debug, Clone, Eq)]
+pub struct File {
     name: PathBuf,
     // internal invariant:
     // data is fix_newlined
     data: String,
+    metadata: BTreeMap<String, String>,
+    origin: Option<PathBuf>,
+    // whether fix_newline added the final newline
+    added_newline: bool,
 }
 
 impl File {
+    /// Creates a file with the given name and contents. A final newline
+    /// is added to non-empty data that lacks one.
     pub fn new<P: AsRef<Path>>(name: P, data: &str) -> File {
-        let name = name.as_ref().to_owned();
-        let mut data = data.to_owned();
-        fix_newline(&mut data);
+        File::from_string(name.as_ref(), data.to_owned())
+    }
+
+    /// Creates a file that takes ownership of the given name and
+    /// contents, without copying them. A final newline is added to
+    /// non-empty data that lacks one.
+    pub fn from_string<P: Into<PathBuf>>(name: P, mut data: String) -> File {
+        let name = name.into();
+        let added_newline = fix_newline(&mut data);
+
+        File {
+            name,
+            data,
+            metadata: BTreeMap::new(),
+            origin: None,
+            added_newline,
+        }
+    }
+
+    /// Creates a file that takes ownership of the given name and
+    /// contents, as in [`File::from_string`].
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the contents are not valid UTF-8. The
+    /// error holds the bytes, so they can be recovered.
+    pub fn from_vec<P: Into<PathBuf>>(name: P, data: Vec<u8>) -> Result<File, FromUtf8Error> {
+        Ok(File::from_string(name, String::from_utf8(data)?))
+    }
+
+    /// Creates a file with the contents read from the reader, which are
+    /// read straight into the file's buffer.
+    ///
+    /// # Errors
+    ///
+    /// This function will error with [`io::ErrorKind::InvalidData`] if
+    /// the contents are not valid UTF-8. Additionally, any errors from
+    /// the reader will be propagated.
+    pub fn from_reader<P: Into<PathBuf>, R: Read>(name: P, reader: R) -> io::Result<File> {
+        File::from_reader_sized(name, reader, 0)
+    }
+
+    /// Like [`File::from_reader`], but reserves room for `size_hint` bytes
+    /// up front, such as the length of a file or a `Content-Length`.
+    pub fn from_reader_sized<P: Into<PathBuf>, R: Read>(
+        name: P,
+        mut reader: R,
+        size_hint: usize,
+    ) -> io::Result<File> {
+        let mut data = String::with_capacity(size_hint.saturating_add(1));
+        reader.read_to_string(&mut data)?;
+        Ok(File::from_string(name, data))
+    }
+
+    /// Returns the name of the file.
+    pub fn name(&self) -> &Path {
+        &self.name
+    }
+
+    /// Returns the name of the file as a path.
+    ///
+    /// This is the same as [`File::name`], which already returns a path
+    /// and keeps that signature for existing callers.
+    pub fn name_path(&self) -> &Path {
+        &self.name
+    }
+
+    /// Returns the name of the file as a string.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the name is not valid UTF-8, which
+    /// only happens for files created with such a name, never for parsed
+    /// ones.
+    pub fn name_str(&self) -> Result<&str, NonUtf8Name> {
+        self.name
+            .to_str()
+            .ok_or_else(|| NonUtf8Name(self.name.clone()))
+    }
+
+    /// Returns the name of the file as bytes, as it is written in the
+    /// file marker on platforms whose paths are UTF-8 or arbitrary bytes.
+    pub fn name_bytes(&self) -> &[u8] {
+        self.name.as_os_str().as_encoded_bytes()
+    }
+
+    /// Returns the contents of the file.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
 
-        File { name, data }
+    /// Returns the contents of the file as a string.
+    ///
+    /// This is the same as [`File::data`]: contents are always valid
+    /// UTF-8, so unlike [`File::name_str`] this cannot fail. Use
+    /// [`File::data_bytes`] for the bytes.
+    pub fn data_str(&self) -> &str {
+        &self.data
+    }
+
+    /// Replaces the contents of the file. A final newline is added to
+    /// non-empty data that lacks one.
+    pub fn set_data(&mut self, data: &str) {
+        self.data = data.to_owned();
+        self.added_newline = fix_newline(&mut self.data);
+    }
+
+    /// Returns the contents of the file as bytes.
+    pub fn data_bytes(&self) -> &[u8] {
+        self.data.as_bytes()
+    }
+
+    /// Returns the metadata attached to the file.
+    ///
+    /// Metadata is written as `key=value` annotations after the name in
+    /// the file marker, as in `-- name # key=value --`, and read back
+    /// when parsing with [`ParseOptions::annotations`]. Keys and values
+    /// must not contain whitespace, and keys must not contain `=`.
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Returns a mutable reference to the metadata attached to the file.
+    pub fn metadata_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.metadata
+    }
+
+    /// Returns where the file came from, if known: the path it was packed
+    /// from, or the txtar file it was read from.
+    pub fn origin(&self) -> Option<&Path> {
+        self.origin.as_deref()
+    }
+
+    /// Sets where the file came from.
+    pub fn set_origin<P: AsRef<Path>>(&mut self, origin: P) {
+        self.origin = Some(origin.as_ref().to_owned());
+    }
+}
+
+impl PartialEq for File {
+    fn eq(&self, other: &File) -> bool {
+        self.name == other.name && self.data == other.data && self.metadata == other.metadata
+    }
+}
+
+impl Hash for File {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.data.hash(state);
+        self.metadata.hash(state);
     }
 }
 
 impl Archive {
-    fn new(comment: &str, files: Vec<File>) -> Archive {
+    /// Creates an empty archive.
+    pub fn new() -> Archive {
+        Archive::default()
+    }
+
+    /// Creates an empty archive with room for at least `capacity` files
+    /// before it reallocates.
+    pub fn with_capacity(capacity: usize) -> Archive {
+        Archive {
+            files: Vec::with_capacity(capacity),
+            ..Archive::default()
+        }
+    }
+
+    /// Returns the number of files the archive can hold without
+    /// reallocating.
+    pub fn capacity(&self) -> usize {
+        self.files.capacity()
+    }
+
+    /// Reserves room for at least `additional` more files.
+    pub fn reserve(&mut self, additional: usize) {
+        self.files.reserve(additional);
+    }
+
+    fn from_parts(comment: &str, files: Vec<File>) -> Archive {
         let mut comment = comment.to_owned();
         fix_newline(&mut comment);
 
-        Archive { comment, files }
+        Archive {
+            comment,
+            files,
+            index: Default::default(),
+        }
+    }
+
+    fn parse_profile(s: &str, profile: Profile) -> Archive {
+        let mut files = Vec::with_capacity(Archive::capacity_hint(s));
+        let (comment, mut next) = split_file_markers(s, profile);
+
+        while let Some((name, s)) = next {
+            let (data, rest) = split_file_markers(s, profile);
+            files.push(File::new(name, data));
+            next = rest;
+        }
+
+        Archive::from_parts(comment, files)
+    }
+
+    /// Returns the files in order.
+    pub fn files(&self) -> &[File] {
+        &self.files
+    }
+
+    /// Returns the number of files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if the archive has no files. It may still have a
+    /// comment.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns the total size of the data of the files in bytes.
+    pub fn total_size(&self) -> usize {
+        self.files.iter().map(|f| f.data.len()).sum()
+    }
+
+    /// Returns the size in bytes of the txtar serialization of the
+    /// archive, including the comment and file markers, without building
+    /// it in memory.
+    pub fn serialized_size(&self) -> usize {
+        struct Counter(usize);
+
+        impl fmt::Write for Counter {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0 += s.len();
+                Ok(())
+            }
+        }
+
+        let mut counter = Counter(0);
+        write!(counter, "{self}").expect("counting cannot fail");
+        counter.0
+    }
+
+    /// Returns an iterator over the files.
+    pub fn iter(&self) -> slice::Iter<'_, File> {
+        self.files.iter()
+    }
+
+    /// Returns an iterator that allows modifying each file.
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, File> {
+        self.files.iter_mut()
+    }
+
+    /// Appends a file.
+    pub fn push(&mut self, file: File) {
+        self.index.push(&file.name, self.files.len());
+        self.files.push(file);
+    }
+
+    /// Inserts a file at position `index`, shifting all files after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`, where `len` is the number of files.
+    pub fn insert(&mut self, index: usize, file: File) {
+        self.files.insert(index, file);
+        self.reindex();
+    }
+
+    /// Converts the archive into a map from file names to contents. Of
+    /// files with the same name, the first is kept, as with
+    /// [`Archive::get`].
+    ///
+    /// # Errors
+    ///
+    /// This function will error if a file name is not valid UTF-8. File
+    /// contents always are.
+    pub fn into_string_map(self) -> Result<HashMap<String, String>, NonUtf8Name> {
+        let mut map = HashMap::with_capacity(self.files.len());
+        for file in self.files {
+            let name = file.name.into_os_string().into_string();
+            let name = name.map_err(|name| NonUtf8Name(name.into()))?;
+            map.entry(name).or_insert(file.data);
+        }
+
+        Ok(map)
+    }
+
+    /// Removes and returns the first file with the given name.
+    pub fn remove<P: AsRef<Path>>(&mut self, name: P) -> Option<File> {
+        let name = name.as_ref();
+        let i = self.files.iter().position(|f| f.name == name)?;
+        let file = self.files.remove(i);
+        self.reindex();
+        Some(file)
+    }
+
+    /// Rewrites the data of every file with the given function, which is
+    /// called with the name and data of each file in order, e.g. to scrub
+    /// timestamps or absolute paths. A final newline is added to
+    /// non-empty data that lacks one.
+    pub fn map_data<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Path, String) -> String,
+    {
+        for file in &mut self.files {
+            let data = mem::take(&mut file.data);
+            file.data = f(&file.name, data);
+            file.added_newline = fix_newline(&mut file.data);
+        }
+    }
+
+    /// Retains only the files for which the predicate returns `true`,
+    /// keeping their order.
+    pub fn retain<F>(&mut self, predicate: F)
+    where
+        F: FnMut(&File) -> bool,
+    {
+        self.files.retain(predicate);
+        self.reindex();
+    }
+
+    /// Renames the first file called `old` to `new`, returning `false` if
+    /// there is no such file.
+    pub fn rename<P, Q>(&mut self, old: P, new: Q) -> bool
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let old = old.as_ref();
+        let Some(file) = self.files.iter_mut().find(|f| f.name == old) else {
+            return false;
+        };
+        file.name = new.as_ref().to_owned();
+        self.reindex();
+        true
+    }
+
+    /// Returns an upper bound on the number of files in the txtar text,
+    /// counting the lines that start with `-- ` without parsing them.
+    ///
+    /// The parser uses this to size its file list up front.
+    pub fn capacity_hint(s: &str) -> usize {
+        usize::from(s.starts_with(MARKER)) + s.matches("\n-- ").count()
     }
 
     /// Serialize the archive as txtar into the I/O stream.
@@ -79,6 +500,33 @@ impl Archive {
         write!(writer, "{}", self)
     }
 
+    /// Renders the archive as txtar, eliding the data of each file after
+    /// `max_bytes_per_file` bytes.
+    ///
+    /// Truncated files end with a `[... N bytes truncated]` line, which
+    /// makes the rendering suitable for error messages and logs, but not
+    /// for parsing back into the original archive.
+    pub fn preview(&self, max_bytes_per_file: usize) -> String {
+        let mut out = self.comment.clone();
+        for File { name, data, .. } in &self.files {
+            out.push_str(&format!("-- {} --\n", name.display()));
+            if data.len() <= max_bytes_per_file {
+                out.push_str(data);
+                continue;
+            }
+
+            let mut end = max_bytes_per_file;
+            while !data.is_char_boundary(end) {
+                end -= 1;
+            }
+            out.push_str(&data[..end]);
+            fix_newline(&mut out);
+            out.push_str(&format!("[... {} bytes truncated]\n", data.len() - end));
+        }
+
+        out
+    }
+
     /// Writes each file in this archive to the directory at the given
     /// path.
     ///
@@ -89,44 +537,147 @@ impl Archive {
     /// overwritten. Additionally, any errors caused by the underlying
     /// I/O operations will be propagated.
     pub fn materialize<P: AsRef<Path>>(&self, path: P) -> Result<(), MaterializeError> {
-        let path = path.as_ref();
-        for File { name, data } in &self.files {
-            let name_path = name.clean();
-            if name_path.starts_with("../") || name_path.is_absolute() {
-                return Err(MaterializeError::DirEscape(
-                    name_path.to_string_lossy().to_string(),
-                ));
-            }
+        self.materialize_with(path, &MaterializeOptions::default())?;
+        Ok(())
+    }
+
+    /// Sets the origin of every file in this archive, for example to the
+    /// path or URL it was read from, before merging it with others.
+    pub fn set_origin<P: AsRef<Path>>(&mut self, origin: P) {
+        for file in &mut self.files {
+            file.set_origin(&origin);
+        }
+    }
+}
 
-            let rel_path = name_path;
-            let path = path.join(rel_path);
-            if let Some(p) = path.parent() {
-                fs::create_dir_all(p)?;
+/// Formats the archive as txtar.
+///
+/// The alternate flag (`{:#}`) instead renders an `ls -l`-style listing
+/// with the size, kind and name of each file, one per line.
+impl Display for Archive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            for file in &self.files {
+                let name = file.name.display();
+                let size = file.data.len();
+                let kind = file.kind();
+                writeln!(f, "{size:>10} {kind:<6} {name}")?;
             }
+            return Ok(());
+        }
 
-            let mut file = fs::File::options()
-                .write(true)
-                .create_new(true)
-                .open(path)?;
-            let mut w = BufWriter::new(&mut file);
-            w.write_all(data.as_bytes())?;
+        self.display_with(&FormatOptions::default()).fmt(f)
+    }
+}
+
+impl IntoIterator for Archive {
+    type Item = File;
+    type IntoIter = vec::IntoIter<File>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Archive {
+    type Item = &'a File;
+    type IntoIter = slice::Iter<'a, File>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Archive {
+    type Item = &'a mut File;
+    type IntoIter = slice::IterMut<'a, File>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.files.iter_mut()
+    }
+}
+
+impl FromIterator<File> for Archive {
+    fn from_iter<I: IntoIterator<Item = File>>(iter: I) -> Archive {
+        let mut archive = Archive::new();
+        archive.extend(iter);
+        archive
+    }
+}
+
+/// Collects `(name, data)` pairs into an archive of files.
+impl<N, D> FromIterator<(N, D)> for Archive
+where
+    N: AsRef<Path>,
+    D: AsRef<str>,
+{
+    fn from_iter<I: IntoIterator<Item = (N, D)>>(iter: I) -> Archive {
+        let mut archive = Archive::new();
+        archive.extend(iter);
+        archive
+    }
+}
+
+impl Extend<File> for Archive {
+    fn extend<I: IntoIterator<Item = File>>(&mut self, iter: I) {
+        for file in iter {
+            self.push(file);
         }
+    }
+}
 
-        Ok(())
+/// Appends `(name, data)` pairs as files.
+impl<N, D> Extend<(N, D)> for Archive
+where
+    N: AsRef<Path>,
+    D: AsRef<str>,
+{
+    fn extend<I: IntoIterator<Item = (N, D)>>(&mut self, iter: I) {
+        self.extend(
+            iter.into_iter()
+                .map(|(name, data)| File::new(name, data.as_ref())),
+        );
     }
 }
 
-impl Display for Archive {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.comment)?;
+/// Returns the data of the first file with the given name.
+///
+/// # Panics
+///
+/// Panics if there is no such file. Use [`Archive::get`] otherwise.
+impl ops::Index<&Path> for Archive {
+    type Output = str;
 
-        for File { name, data } in &self.files {
-            let name = name.display();
-            writeln!(f, "-- {name} --")?;
-            write!(f, "{data}")?;
+    fn index(&self, name: &Path) -> &str {
+        match self.get(name) {
+            Some(file) => &file.data,
+            None => panic!("no file named {}", name.display()),
         }
+    }
+}
 
-        Ok(())
+impl ops::Index<&str> for Archive {
+    type Output = str;
+
+    fn index(&self, name: &str) -> &str {
+        &self[Path::new(name)]
+    }
+}
+
+/// Concatenates two archives, appending the files of the right-hand side
+/// and joining non-empty comments with a blank line.
+impl Add for Archive {
+    type Output = Archive;
+
+    fn add(mut self, rhs: Archive) -> Archive {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for Archive {
+    fn add_assign(&mut self, rhs: Archive) {
+        self.append(rhs, &CommentPolicy::Concat("\n".to_owned()));
     }
 }
 
@@ -141,20 +692,7 @@ impl TryFrom<&[u8]> for Archive {
 
 impl From<&str> for Archive {
     fn from(s: &str) -> Archive {
-        let (comment, mut name, mut s) = split_file_markers(s);
-        let mut files = Vec::new();
-
-        while !name.is_empty() {
-            let (data, next_name, rest) = split_file_markers(s);
-
-            let file = File::new(name, data);
-            files.push(file);
-
-            name = next_name;
-            s = rest;
-        }
-
-        Archive::new(comment, files)
+        Archive::parse_profile(s, Profile::Lenient)
     }
 }
 
@@ -168,45 +706,88 @@ pub fn from_bytes(slice: &[u8]) -> Result<Archive, std::str::Utf8Error> {
     Archive::try_from(slice)
 }
 
-fn split_file_markers(s: &str) -> (&str, &str, &str) {
+/// Returns the comment of txtar data without parsing the files.
+///
+/// The comment is returned as it appears in the text, so it lacks a final
+/// newline if the text has no files and does not end with one.
+pub fn read_comment(s: &str) -> &str {
+    split_file_markers(s, Profile::Lenient).0
+}
+
+/// Returns an iterator over the file names in txtar data, which finds
+/// each name only when it is requested.
+///
+/// # Examples
+///
+/// ```rust
+/// let txt = "comment\n-- a.txt --\nhello\n-- b.txt --\n";
+/// let names: Vec<&str> = txtar::list_names(txt).collect();
+/// assert_eq!(names, ["a.txt", "b.txt"]);
+/// ```
+pub fn list_names(s: &str) -> impl Iterator<Item = &str> {
+    let mut next = split_file_markers(s, Profile::Lenient).1;
+    std::iter::from_fn(move || {
+        let (name, rest) = next?;
+        next = split_file_markers(rest, Profile::Lenient).1;
+        Some(name)
+    })
+}
+
+const MARKER: &str = "-- ";
+const MARKER_END: &str = " --";
+
+/// Splits `s` at the first file marker line, returning the text before
+/// the marker and, if there is a marker line, the file name and the text
+/// after it.
+fn split_file_markers(s: &str, profile: Profile) -> (&str, Option<(&str, &str)>) {
     const NEWLINE_MARKER: &str = "\n-- ";
-    const MARKER: &str = "-- ";
-    const MARKER_END: &str = " --";
-
-    let (prefix, rest) = if s.starts_with(MARKER) {
-        ("", s)
-    } else {
-        match s.find(NEWLINE_MARKER) {
-            None => return (s, "", ""),
-            Some(offset) => s.split_at(offset + 1),
+
+    let mut start = 0;
+    loop {
+        if !s[start..].starts_with(MARKER) {
+            match s[start..].find(NEWLINE_MARKER) {
+                None => return (s, None),
+                Some(offset) => start += offset + 1,
+            }
         }
-    };
-    debug_assert!(rest.starts_with(MARKER));
 
-    let (filename, suffix) = match rest.split_once('\n') {
-        None if rest.ends_with(MARKER_END) => (rest, ""),
-        None => return (s, "", ""),
-        Some((n, pf)) => (n, pf),
-    };
+        let rest = &s[start..];
+        let (line, suffix) = rest.split_once('\n').unwrap_or((rest, ""));
+        if let Some(name) = marker_name(line, profile) {
+            return (&s[..start], Some((name, suffix)));
+        }
+        start += line.len();
+    }
+}
 
-    let filename = filename.trim_end_matches('\r');
-    debug_assert!(filename.ends_with(MARKER_END));
+/// Returns the file name if `line` is a file marker line.
+///
+/// Go's implementation does not ignore a trailing carriage return, so
+/// the result depends on the profile. Like Go's, it never accepts an
+/// empty name.
+fn marker_name(line: &str, profile: Profile) -> Option<&str> {
+    let line = match profile {
+        Profile::GoCompat => line,
+        Profile::Lenient | Profile::Strict => line.trim_end_matches('\r'),
+    };
+    let name = line.strip_prefix(MARKER)?.strip_suffix(MARKER_END)?.trim();
 
-    let filename = filename
-        .strip_prefix(MARKER)
-        .and_then(|filename| filename.strip_suffix(MARKER_END))
-        .unwrap()
-        .trim();
-    (prefix, filename, suffix)
+    (!name.is_empty()).then_some(name)
 }
 
-fn fix_newline(s: &mut String) {
-    if !s.is_empty() && !s.ends_with('\n') {
+/// Appends a newline to `s` if it is not empty and does not end with one,
+/// returning whether it did.
+fn fix_newline(s: &mut String) -> bool {
+    let fix = !s.is_empty() && !s.ends_with('\n');
+    if fix {
         s.push('\n');
     }
+    fix
 }
 
 #[cfg(test)]
+// `assert_str_eq` is deprecated in newer releases of similar-asserts.
+#[allow(deprecated)]
 mod tests {
     use super::*;
     use assert_fs::{prelude::*, TempDir};
@@ -244,7 +825,7 @@ hello world";
         {
             let crlf = "blah\r\n-- hello --\r\nhello\r\n";
             let expected = "\
-Archive { comment: \"blah\\r\\n\", files: [File { name: \"hello\", data: \"hello\\r\\n\" }] }";
+Archive { comment: \"blah\\r\\n\", files: [File { name: \"hello\", data: \"hello\\r\\n\", metadata: {}, origin: None, added_newline: false }], index: Index(None) }";
 
             let arch = format!("{:?}", Archive::from(crlf));
             assert_str_eq!(&arch, expected, "parse[CRLF input]",);
@@ -258,6 +839,320 @@ Archive { comment: \"blah\\r\\n\", files: [File { name: \"hello\", data: \"hello
         }
     }
 
+    #[test]
+    fn format_listing() {
+        let arch = Archive::from(BASIC);
+        let expected = concat!(
+            "        13 text   file1\n",
+            "        13 text   foo\n",
+            "         0 empty  empty\n",
+            "        12 text   noNL\n",
+        );
+        assert_eq!(format!("{arch:#}"), expected);
+    }
+
+    #[test]
+    fn preview() {
+        let arch = Archive::from("comment\n-- short --\nhi\n-- long --\nhello world\n");
+        let expected = "comment\n-- short --\nhi\n-- long --\nhello\n[... 7 bytes truncated]\n";
+        assert_eq!(arch.preview(5), expected);
+        assert_eq!(arch.preview(100), arch.to_string());
+    }
+
+    #[test]
+    fn concat() {
+        let base = Archive::from("base\n-- a --\na\n");
+        let overrides = Archive::from("-- b --\nb\n");
+        let platform = Archive::from("linux\n-- a --\nA\n");
+
+        let arch = base + overrides + platform;
+        assert_eq!(
+            arch.to_string(),
+            "base\n\nlinux\n-- a --\na\n-- b --\nb\n-- a --\nA\n"
+        );
+    }
+
+    #[test]
+    fn origin() {
+        let mut base = Archive::from("-- a --\n1\n");
+        let mut overlay = Archive::from("-- a --\n2\n");
+        base.set_origin("base.txtar");
+        overlay.set_origin("overlay.txtar");
+
+        let merged = base + overlay;
+        let origins: Vec<_> = merged.files.iter().map(|f| f.origin()).collect();
+        assert_eq!(
+            origins,
+            [
+                Some(Path::new("base.txtar")),
+                Some(Path::new("overlay.txtar"))
+            ]
+        );
+        assert_eq!(merged, Archive::from("-- a --\n1\n-- a --\n2\n"));
+    }
+
+    #[test]
+    fn archive_is_owned() {
+        fn assert_owned<T: Send + Sync + 'static>(_: &T) {}
+
+        let archive = {
+            let source = String::from("-- a --\nhello\n");
+            Archive::from(source.as_str())
+        };
+        assert_owned(&archive);
+    }
+
+    #[test]
+    fn partial_parse() {
+        assert_eq!(read_comment(BASIC), "comment1\ncomment2\n");
+        assert_eq!(read_comment("no files"), "no files");
+        assert_eq!(
+            list_names(BASIC).collect::<Vec<_>>(),
+            ["file1", "foo", "empty", "noNL"]
+        );
+        assert_eq!(list_names("").next(), None);
+    }
+
+    #[test]
+    fn accessors() {
+        let archive = Archive::from(BASIC);
+        assert_eq!(archive.comment(), "comment1\ncomment2\n");
+        assert_eq!(archive.comment_bytes(), b"comment1\ncomment2\n");
+
+        let files = archive.files();
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0].name(), Path::new("file1"));
+        assert_eq!(files[0].data(), "File 1 text.\n");
+        assert_eq!(files[3].data_bytes(), b"hello world\n");
+        assert_eq!(files[3].name_str(), Ok("noNL"));
+        assert_eq!(files[3].name_bytes(), b"noNL");
+        assert_eq!(files[3].name_path(), Path::new("noNL"));
+        assert_eq!(files[3].data_str(), "hello world\n");
+    }
+
+    #[test]
+    fn clone() {
+        let pristine = Archive::from(BASIC);
+        let mut copy = pristine.clone();
+        copy.build_index();
+        copy.get_mut("foo").unwrap().set_data("changed");
+        assert_eq!(pristine.get("foo").unwrap().data(), "File 2 text.\n");
+
+        let indexed = copy.clone();
+        assert!(indexed.is_indexed());
+        assert_eq!(indexed, copy);
+        assert_ne!(indexed, pristine);
+    }
+
+    #[test]
+    fn hash_as_key() {
+        use std::collections::HashSet;
+
+        let mut indexed = Archive::from(BASIC);
+        indexed.build_index();
+        let keys = HashSet::from([Archive::from(BASIC), indexed, Archive::new()]);
+        assert_eq!(keys.len(), 2);
+        let archive = Archive::from("-- a --\n-- a --\n-- b --\n");
+        let files: HashSet<&File> = archive.iter().collect();
+        assert_eq!(files.len(), 2);
+    }
+
+    #[test]
+    fn owned_files() {
+        let name = format!("gen/{}", 1);
+        let file = File::from_string(name, "x".repeat(3));
+        assert_eq!(file, File::new("gen/1", "xxx\n"));
+
+        let file = File::from_vec(PathBuf::from("b"), b"bytes\n".to_vec()).unwrap();
+        assert_eq!(file.data(), "bytes\n");
+        let err = File::from_vec("c", vec![b'a', 0xff]).unwrap_err();
+        assert_eq!(err.into_bytes(), [b'a', 0xff]);
+
+        let file = File::from_reader("d", io::repeat(b'x').take(5)).unwrap();
+        assert_eq!(file.data(), "xxxxx\n");
+        let file = File::from_reader_sized("e", &b"e\n"[..], 2).unwrap();
+        assert_eq!(file.data(), "e\n");
+        let err = File::from_reader("f", &b"\xff"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn push_insert() {
+        let mut archive = Archive::new();
+        archive.build_index();
+        archive.push(File::new("b", "2"));
+        archive.push(File::new("c", "3\n"));
+        archive.insert(0, File::new("a", ""));
+        archive.insert(3, File::new("b", "4"));
+
+        assert_eq!(
+            archive.to_string(),
+            "-- a --\n-- b --\n2\n-- c --\n3\n-- b --\n4\n"
+        );
+        assert_eq!(archive.get("b").unwrap().data(), "2\n");
+        assert_eq!(archive.get("c").unwrap().data(), "3\n");
+    }
+
+    #[test]
+    fn remove_rename() {
+        let mut archive = Archive::from("-- a --\n1\n-- b --\n2\n-- a --\n3\n");
+        archive.build_index();
+
+        assert_eq!(archive.remove("a").map(|f| f.data), Some("1\n".into()));
+        assert_eq!(archive.remove("c"), None);
+        assert!(archive.rename("b", "sub/b"));
+        assert!(!archive.rename("b", "c"));
+
+        assert_eq!(archive.to_string(), "-- sub/b --\n2\n-- a --\n3\n");
+        assert_eq!(archive.get("a").unwrap().data(), "3\n");
+        assert!(archive.get("b").is_none());
+
+        archive.retain(|f| f.name().extension().is_none() && f.name() != Path::new("a"));
+        assert_eq!(archive.to_string(), "-- sub/b --\n2\n");
+        assert!(archive.get("a").is_none());
+    }
+
+    #[test]
+    fn map_data() {
+        let mut archive = Archive::from("-- a --\n/home/alice/x\n-- b --\n-- c --\nc\n");
+        archive.map_data(|name, data| {
+            if name == Path::new("c") {
+                return "no newline".to_owned();
+            }
+            data.replace("/home/alice", "$HOME")
+        });
+        assert_eq!(
+            archive.to_string(),
+            "-- a --\n$HOME/x\n-- b --\n-- c --\nno newline\n"
+        );
+        assert!(archive.files[2].added_newline);
+    }
+
+    #[test]
+    fn into_string_map() {
+        let archive = Archive::from("-- a --\n1\n-- b/c --\n-- a --\n2\n");
+        let map = archive.into_string_map().unwrap();
+        assert_eq!(
+            map,
+            HashMap::from([("a".into(), "1\n".into()), ("b/c".into(), "".into())])
+        );
+
+        #[cfg(unix)]
+        {
+            use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+            let name = Path::new(OsStr::from_bytes(b"bad\xff"));
+            let mut archive = Archive::new();
+            archive.push(File::new(name, "x"));
+            assert_eq!(archive.files[0].name_bytes(), b"bad\xff");
+            let err = archive.files[0].name_str().unwrap_err();
+            assert_eq!(err, NonUtf8Name(name.to_owned()));
+            let err = archive.into_string_map().unwrap_err();
+            assert_eq!(err, NonUtf8Name(name.to_owned()));
+        }
+    }
+
+    #[test]
+    fn iterators() {
+        let mut archive = Archive::from(BASIC);
+        let names: Vec<_> = archive.iter().map(|f| f.name().to_owned()).collect();
+        assert_eq!(names, ["file1", "foo", "empty", "noNL"].map(PathBuf::from));
+
+        for file in &mut archive {
+            file.metadata_mut().insert("seen".into(), "yes".into());
+        }
+        assert!(archive.iter_mut().all(|f| f.metadata()["seen"] == "yes"));
+        assert_eq!((&archive).into_iter().count(), 4);
+
+        let files: Vec<File> = archive.into_iter().collect();
+        assert_eq!(files[3].data(), "hello world\n");
+    }
+
+    #[test]
+    fn collect_extend() {
+        let mut archive: Archive = (1..=2).map(|i| (format!("f{i}"), i.to_string())).collect();
+        archive.extend([("a/b", "x")]);
+        archive.extend([File::new("c", "")]);
+        assert_eq!(
+            archive.to_string(),
+            "-- f1 --\n1\n-- f2 --\n2\n-- a/b --\nx\n-- c --\n"
+        );
+
+        let copy: Archive = archive.into_iter().collect();
+        assert_eq!(copy.files().len(), 4);
+    }
+
+    #[test]
+    fn index_by_name() {
+        let archive = Archive::from(BASIC);
+        assert_eq!(&archive["foo"], "File 2 text.\n");
+        assert_eq!(&archive[Path::new("empty")], "");
+    }
+
+    #[test]
+    #[should_panic(expected = "no file named missing")]
+    fn index_missing() {
+        let _ = &Archive::from(BASIC)["missing"];
+    }
+
+    #[test]
+    fn sizes() {
+        let archive = Archive::from(BASIC);
+        assert_eq!(archive.len(), 4);
+        assert!(!archive.is_empty());
+        assert_eq!(archive.total_size(), 13 + 13 + 12);
+        assert_eq!(archive.serialized_size(), archive.to_string().len());
+
+        let archive = Archive::from("comment only");
+        assert!(archive.is_empty());
+        assert_eq!(archive.total_size(), 0);
+        assert_eq!(archive.serialized_size(), 13);
+    }
+
+    #[test]
+    fn capacity_hint() {
+        assert_eq!(Archive::capacity_hint(""), 0);
+        assert_eq!(Archive::capacity_hint(BASIC), 4);
+        assert_eq!(Archive::capacity_hint("-- a --\n-- b\n"), 2);
+
+        let mut archive = Archive::with_capacity(3);
+        assert!(archive.capacity() >= 3);
+        assert_eq!(archive, Archive::default());
+        archive.reserve(10);
+        assert!(archive.capacity() >= 10);
+    }
+
+    #[test]
+    fn parse_never_panics() {
+        const ALPHABET: [char; 5] = ['-', ' ', '\n', '\r', 'a'];
+        const MAX_LEN: u32 = 8;
+
+        for len in 0..=MAX_LEN {
+            for mut n in 0..ALPHABET.len().pow(len) {
+                let mut s = String::new();
+                for _ in 0..len {
+                    s.push(ALPHABET[n % ALPHABET.len()]);
+                    n /= ALPHABET.len();
+                }
+
+                let arch = Archive::from(s.as_str());
+                assert_eq!(arch.files.len(), spans::count(&s), "count[{s:?}]");
+                let reparsed = Archive::from(arch.to_string().as_str());
+                assert_eq!(reparsed, arch, "roundtrip[{s:?}]");
+            }
+        }
+    }
+
+    #[test]
+    fn parse_non_markers() {
+        check_parse_format("no name", "-- --\n--  --\n", "-- --\n--  --\n");
+        check_parse_format(
+            "unterminated marker",
+            "-- a --\n-- b\n-- c --",
+            "-- a --\n-- b\n-- c --\n",
+        );
+    }
+
     fn check_parse_format(name: &str, txtar: &str, expected: &str) {
         let arch = Archive::from(txtar);
         let txtar = arch.to_string();
@@ -0,0 +1,962 @@
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+use clean_path::Clean;
+
+use crate::{cache, workspace, Archive, Event, File, MaterializeError, NameRules};
+
+pub(crate) const SPARSE_BLOCK: usize = 4096;
+
+/// Decides where the files of an archive are written when materializing.
+pub trait PathPolicy {
+    /// Returns the path, relative to the target directory, that the file
+    /// with the given name is written to, or an error if it must not be
+    /// written at all.
+    ///
+    /// The policy is responsible for keeping files inside the target
+    /// directory. Stricter policies usually start by calling
+    /// [`ContainedPaths`].
+    fn check(&self, name: &Path) -> Result<PathBuf, MaterializeError>;
+}
+
+impl<F> PathPolicy for F
+where
+    F: Fn(&Path) -> Result<PathBuf, MaterializeError>,
+{
+    fn check(&self, name: &Path) -> Result<PathBuf, MaterializeError> {
+        self(name)
+    }
+}
+
+/// The default path policy, which cleans names of `.` and `..`
+/// components and rejects those that would escape the target directory.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ContainedPaths;
+
+impl PathPolicy for ContainedPaths {
+    fn check(&self, name: &Path) -> Result<PathBuf, MaterializeError> {
+        let name_path = name.clean();
+        if name_path.starts_with("../") || name_path.is_absolute() {
+            return Err(MaterializeError::DirEscape(
+                name_path.to_string_lossy().to_string(),
+            ));
+        }
+        Ok(name_path)
+    }
+}
+
+/**
+Options controlling how an archive is written to disk.
+
+# Examples
+
+```rust no_run
+use std::path::Path;
+use txtar::{Archive, ContainedPaths, MaterializeError, MaterializeOptions, PathPolicy};
+
+let options = MaterializeOptions::new().path_policy(|name: &Path| {
+    let path = ContainedPaths.check(name)?;
+    if path.components().count() > 3 {
+        let name = name.to_string_lossy().to_string();
+        return Err(MaterializeError::Rejected(name, "too deeply nested"));
+    }
+    Ok(path)
+});
+
+let archive = Archive::from("-- a/b/c/d --\n");
+assert!(archive.materialize_with("/tmp/somedir", &options).is_err());
+```
+**/
+#[derive(Default, Clone)]
+pub struct MaterializeOptions {
+    sparse: bool,
+    read_only: bool,
+    ignore_conditions: bool,
+    mtime_base: Option<SystemTime>,
+    preflight: bool,
+    case_insensitive: bool,
+    workspace_only: bool,
+    event_log: bool,
+    name_rules: Option<NameRules>,
+    path_policy: Option<Arc<dyn PathPolicy + Send + Sync>>,
+}
+
+impl MaterializeOptions {
+    pub fn new() -> MaterializeOptions {
+        MaterializeOptions::default()
+    }
+
+    /// Sets whether long runs of NUL bytes are skipped over instead of
+    /// written, leaving holes in the created files on filesystems that
+    /// support sparse files.
+    ///
+    /// Holes recorded in the `sparse` [metadata](File::metadata) of a file,
+    /// as packed with [`PackOptions::sparse`](crate::PackOptions::sparse),
+    /// are put back either way, and are only skipped over with this
+    /// option.
+    pub fn sparse(mut self, sparse: bool) -> MaterializeOptions {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Sets whether the created files are marked read-only, so code that
+    /// tries to modify them fails. Use [`restore_writable`] with the
+    /// returned [`Manifest`] to undo this before removing the files.
+    ///
+    /// WASI has no file permissions, so materializing with this option
+    /// fails with [`io::ErrorKind::Unsupported`] there.
+    pub fn read_only(mut self, read_only: bool) -> MaterializeOptions {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Sets whether files are skipped when the condition in their `if`
+    /// [metadata](File::metadata) does not hold for the current platform.
+    /// This is enabled by default.
+    ///
+    /// A condition is a comma-separated list of platforms, any of which
+    /// must match, such as `if=linux,macos`. A platform is an operating
+    /// system as in [`std::env::consts::OS`] or a family as in
+    /// [`std::env::consts::FAMILY`], and is negated by a leading `!`, as
+    /// in `if=!windows`.
+    pub fn conditions(mut self, conditions: bool) -> MaterializeOptions {
+        self.ignore_conditions = !conditions;
+        self
+    }
+
+    /// Sets the modification times of the created files from their
+    /// position in the archive instead of the current time: the first
+    /// file gets `base`, the second `base` plus one second, and so on.
+    ///
+    /// This makes tools that compare timestamps behave the same on every
+    /// run. [`SystemTime::UNIX_EPOCH`] is a common choice of base.
+    pub fn mtimes(mut self, base: SystemTime) -> MaterializeOptions {
+        self.mtime_base = Some(base);
+        self
+    }
+
+    /// Sets whether each target directory is checked before anything is
+    /// written to it, by writing and removing a file as large as the
+    /// files that go there. This catches read-only directories and full
+    /// filesystems up front, with [`MaterializeError::NotWritable`], at
+    /// the cost of writing the data twice.
+    pub fn preflight(mut self, preflight: bool) -> MaterializeOptions {
+        self.preflight = preflight;
+        self
+    }
+
+    /// Sets whether paths that differ only in case are rejected up front
+    /// with [`MaterializeError::CaseCollision`], as they would overwrite
+    /// each other on the case-insensitive filesystems that are the
+    /// default on macOS and Windows.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> MaterializeOptions {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Sets whether target directories outside the Cargo workspace are
+    /// refused with [`MaterializeError::OutsideWorkspace`] before anything
+    /// is written. The system temporary directory is allowed as well, as
+    /// that is where tests usually materialize archives.
+    ///
+    /// This guards test suites against writing into a home directory or
+    /// elsewhere when a path is misconfigured. See [`workspace_root`] for
+    /// how the workspace is found.
+    ///
+    /// [`workspace_root`]: crate::workspace_root
+    pub fn workspace_only(mut self, workspace_only: bool) -> MaterializeOptions {
+        self.workspace_only = workspace_only;
+        self
+    }
+
+    /// Sets whether a log of what the materialization did is recorded in
+    /// the returned manifests, for auditing fixture extraction in CI. See
+    /// [`Manifest::events`].
+    pub fn event_log(mut self, event_log: bool) -> MaterializeOptions {
+        self.event_log = event_log;
+        self
+    }
+
+    /// Sets rules that file names must follow, checked before anything is
+    /// written. Files that break them are rejected with
+    /// [`MaterializeError::Rejected`].
+    pub fn name_rules(mut self, rules: NameRules) -> MaterializeOptions {
+        self.name_rules = Some(rules);
+        self
+    }
+
+    /// Sets the policy that checks file names and turns them into paths
+    /// to write to. The default is [`ContainedPaths`].
+    pub fn path_policy<P>(mut self, policy: P) -> MaterializeOptions
+    where
+        P: PathPolicy + Send + Sync + 'static,
+    {
+        self.path_policy = Some(Arc::new(policy));
+        self
+    }
+}
+
+impl MaterializeOptions {
+    /// Turns a file name into the path it is written to, relative to the
+    /// target directory, using the path policy.
+    fn resolve(&self, name: &Path) -> Result<PathBuf, MaterializeError> {
+        match &self.path_policy {
+            Some(policy) => policy.check(name),
+            None => ContainedPaths.check(name),
+        }
+    }
+}
+
+impl fmt::Debug for MaterializeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MaterializeOptions")
+            .field("sparse", &self.sparse)
+            .field("read_only", &self.read_only)
+            .field("ignore_conditions", &self.ignore_conditions)
+            .field("mtime_base", &self.mtime_base)
+            .field("preflight", &self.preflight)
+            .field("case_insensitive", &self.case_insensitive)
+            .field("workspace_only", &self.workspace_only)
+            .field("event_log", &self.event_log)
+            .field("name_rules", &self.name_rules)
+            .field("path_policy", &self.path_policy.is_some())
+            .finish()
+    }
+}
+
+/// The files and directories created by [`Archive::materialize_with`],
+/// relative to the directory the archive was written to.
+///
+/// Pass it to [`cleanup`] to remove exactly those paths again.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Manifest {
+    files: Vec<PathBuf>,
+    dirs: Vec<PathBuf>,
+    events: Option<Vec<Event>>,
+}
+
+impl Manifest {
+    /// Returns the created files, in the order they were written.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Returns the created directories, parents before their children.
+    /// The target directory itself is included as an empty path if it
+    /// was created.
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Returns the events of the materialization, in the order they
+    /// happened, if it was run with [`MaterializeOptions::event_log`].
+    pub fn events(&self) -> &[Event] {
+        self.events.as_deref().unwrap_or_default()
+    }
+
+    /// Writes the events as JSON Lines, one object per event, such as
+    /// `{"event":"file","path":"a/b","size":3,"hash":"..."}`. See
+    /// [`Event`] for the kinds of events.
+    pub fn events_to_jsonl<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for event in self.events() {
+            writer.write_all(event.to_json().as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn log<F: FnOnce() -> Event>(&mut self, event: F) {
+        if let Some(events) = &mut self.events {
+            events.push(event());
+        }
+    }
+
+    fn create_dirs(&mut self, root: &Path, dir: &Path) -> io::Result<()> {
+        let missing: Vec<&Path> = dir
+            .ancestors()
+            .take_while(|d| !root.join(d).is_dir())
+            .collect();
+        for dir in missing.into_iter().rev() {
+            fs::create_dir_all(root.join(dir))?;
+            self.log(|| Event::DirCreated(dir.to_owned()));
+            self.dirs.push(dir.to_owned());
+        }
+
+        Ok(())
+    }
+}
+
+/// Removes the files and directories listed in the manifest from the
+/// directory at the given path, leaving anything else in place.
+///
+/// Files that no longer exist are skipped, and directories that are not
+/// empty, for example because other files were created in them, are kept.
+pub fn cleanup<P: AsRef<Path>>(path: P, manifest: &Manifest) -> io::Result<()> {
+    let path = path.as_ref();
+    for file in &manifest.files {
+        match fs::remove_file(path.join(file)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    for dir in manifest.dirs.iter().rev() {
+        match fs::remove_dir(path.join(dir)) {
+            Err(e)
+                if !matches!(
+                    e.kind(),
+                    io::ErrorKind::NotFound | io::ErrorKind::DirectoryNotEmpty
+                ) =>
+            {
+                return Err(e)
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+impl Archive {
+    /// Writes each file in this archive to the directory at the given
+    /// path according to the given options, returning a [`Manifest`] of
+    /// the created paths.
+    ///
+    /// # Errors
+    ///
+    /// This function will error in the event a file would be written
+    /// outside of the directory or if an existing file would be
+    /// overwritten. Additionally, any errors caused by the underlying
+    /// I/O operations will be propagated.
+    pub fn materialize_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &MaterializeOptions,
+    ) -> Result<Manifest, MaterializeError> {
+        let mut manifests = self.materialize_split([("", path)], options)?;
+        Ok(manifests.remove(0))
+    }
+
+    /// Writes the files of this archive below several directories, chosen
+    /// by the prefix of their names, and returns a [`Manifest`] for each
+    /// directory in the order they were given.
+    ///
+    /// Each file is written to the directory of the longest prefix its
+    /// name starts with, with the prefix removed. An empty prefix matches
+    /// every file. All names are checked before anything is written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust no_run
+    /// use txtar::{Archive, MaterializeOptions};
+    ///
+    /// let archive = Archive::from("-- input/a --\n1\n-- expected/a --\n2\n");
+    /// let roots = [("input", "./work"), ("expected", "./golden")];
+    /// archive
+    ///     .materialize_split(roots, &MaterializeOptions::new())
+    ///     .unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the name of a file matches no prefix,
+    /// in addition to the cases described in
+    /// [`Archive::materialize_with`].
+    pub fn materialize_split<I, P, Q>(
+        &self,
+        roots: I,
+        options: &MaterializeOptions,
+    ) -> Result<Vec<Manifest>, MaterializeError>
+    where
+        I: IntoIterator<Item = (P, Q)>,
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let roots: Vec<(PathBuf, PathBuf)> = roots
+            .into_iter()
+            .map(|(prefix, dir)| (prefix.as_ref().clean(), dir.as_ref().to_owned()))
+            .collect();
+        if options.workspace_only {
+            for (_, path) in &roots {
+                workspace::check_in_workspace(path)?;
+            }
+        }
+
+        let route = |name_path: &Path| {
+            roots
+                .iter()
+                .enumerate()
+                .rev()
+                .filter_map(|(i, (prefix, _))| {
+                    let prefix = if prefix == Path::new(".") {
+                        Path::new("")
+                    } else {
+                        prefix
+                    };
+                    let rel = name_path.strip_prefix(prefix).ok()?;
+                    let depth = prefix.components().count();
+                    (rel != Path::new("")).then(|| (depth, i, rel.to_owned()))
+                })
+                .max_by_key(|&(depth, ..)| depth)
+                .map(|(_, root, rel_path)| (root, rel_path))
+        };
+
+        let mut targets = Vec::new();
+        let mut skipped = Vec::new();
+        for (position, file) in self.files.iter().enumerate() {
+            if !options.ignore_conditions && !condition_holds(file) {
+                if options.event_log {
+                    if let Some((root, rel_path)) = route(&file.name.clean()) {
+                        skipped.push((root, rel_path, position));
+                    }
+                }
+                continue;
+            }
+
+            if let Some(rules) = &options.name_rules {
+                rules.check(&file.name).map_err(|reason| {
+                    MaterializeError::Rejected(file.name.to_string_lossy().to_string(), reason)
+                })?;
+            }
+            let name_path = options.resolve(&file.name)?;
+
+            let Some((root, rel_path)) = route(&name_path) else {
+                return Err(MaterializeError::Unmapped(
+                    name_path.to_string_lossy().to_string(),
+                ));
+            };
+            targets.push((root, rel_path, position, file));
+        }
+
+        if options.case_insensitive {
+            let mut seen = HashMap::new();
+            for (root, rel_path, _, _) in &targets {
+                let key = (&roots[*root].1, rel_path.to_string_lossy().to_lowercase());
+                if let Some(other) = seen.insert(key, rel_path) {
+                    if other != rel_path {
+                        return Err(MaterializeError::CaseCollision(
+                            other.to_string_lossy().to_string(),
+                            rel_path.to_string_lossy().to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        if options.preflight {
+            let mut sizes = vec![None; roots.len()];
+            for (root, _, _, file) in &targets {
+                *sizes[*root].get_or_insert(0) += file.data.len();
+            }
+            for ((_, path), size) in roots.iter().zip(sizes) {
+                if let Some(size) = size {
+                    preflight(path, size)?;
+                }
+            }
+        }
+
+        let manifest = Manifest {
+            events: options.event_log.then(Vec::new),
+            ..Manifest::default()
+        };
+        let mut manifests = vec![manifest; roots.len()];
+        let mut skipped = skipped.into_iter().peekable();
+        for (root, rel_path, position, File { data, metadata, .. }) in targets {
+            while let Some((root, rel_path, _)) = skipped.next_if(|s| s.2 < position) {
+                manifests[root].log(|| Event::Skipped(rel_path));
+            }
+            let holes = match metadata.get(SPARSE) {
+                Some(holes) => parse_holes(holes).ok_or_else(|| {
+                    let msg = format!("{}: invalid sparse metadata", rel_path.display());
+                    io::Error::new(io::ErrorKind::InvalidData, msg)
+                })?,
+                None => Vec::new(),
+            };
+            let path = &roots[root].1;
+            let manifest = &mut manifests[root];
+            if let Some(p) = rel_path.parent() {
+                manifest.create_dirs(path, p)?;
+            }
+
+            let mut file = fs::File::options()
+                .write(true)
+                .create_new(true)
+                .open(path.join(&rel_path))?;
+            manifest.log(|| Event::FileWritten {
+                path: rel_path.clone(),
+                size: data.len(),
+                hash: cache::data_hash(data),
+            });
+            manifest.files.push(rel_path);
+            write_data(&mut file, data.as_bytes(), &holes, options.sparse)?;
+            if let Some(base) = options.mtime_base {
+                file.set_modified(base + Duration::from_secs(position as u64))?;
+            }
+            if options.read_only {
+                let mut permissions = file.metadata()?.permissions();
+                permissions.set_readonly(true);
+                file.set_permissions(permissions)?;
+            }
+        }
+        for (root, rel_path, _) in skipped {
+            manifests[root].log(|| Event::Skipped(rel_path));
+        }
+
+        Ok(manifests)
+    }
+
+    /// Makes the files of this archive in the directory at the given path
+    /// writable again after materializing them with
+    /// [`MaterializeOptions::read_only`] and the same options.
+    ///
+    /// Names are resolved with the [path policy](MaterializeOptions::path_policy)
+    /// of the options, and names it rejects or that would resolve outside
+    /// the directory are skipped, as are files that no longer exist. Use
+    /// [`restore_writable`] with the manifest after
+    /// [`Archive::materialize_split`].
+    pub fn restore_writable<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &MaterializeOptions,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        for File { name, .. } in &self.files {
+            match options.resolve(name) {
+                Ok(rel_path) if is_contained(&rel_path) => make_writable(&path.join(rel_path))?,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Makes the files listed in the manifest, in the directory at the given
+/// path, writable again after materializing them with
+/// [`MaterializeOptions::read_only`]. Files that no longer exist are
+/// skipped.
+pub fn restore_writable<P: AsRef<Path>>(path: P, manifest: &Manifest) -> io::Result<()> {
+    let path = path.as_ref();
+    for file in &manifest.files {
+        make_writable(&path.join(file))?;
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the relative path stays inside the directory it is
+/// joined to.
+fn is_contained(path: &Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+fn make_writable(path: &Path) -> io::Result<()> {
+    let mut permissions = match fs::metadata(path) {
+        Ok(metadata) => metadata.permissions(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    set_writable(&mut permissions);
+    fs::set_permissions(path, permissions)
+}
+
+/// The metadata key holding the platform condition of a file.
+const CONDITION: &str = "if";
+
+fn condition_holds(file: &File) -> bool {
+    let Some(condition) = file.metadata.get(CONDITION) else {
+        return true;
+    };
+    let is_platform = |p: &str| p == env::consts::OS || p == env::consts::FAMILY;
+
+    condition.split(',').any(|p| match p.strip_prefix('!') {
+        Some(p) => !is_platform(p),
+        None => is_platform(p),
+    })
+}
+
+/// Checks that `size` bytes can be written below the directory at the
+/// given path, or the closest ancestor that exists.
+fn preflight(path: &Path, size: usize) -> Result<(), MaterializeError> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = path
+        .ancestors()
+        .find(|d| d.is_dir())
+        .unwrap_or(Path::new("."));
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let probe = dir.join(format!(".txtar-preflight-{}-{n}", process::id()));
+    let not_writable = |e| MaterializeError::NotWritable(path.to_string_lossy().to_string(), e);
+
+    let mut file = fs::File::options()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(not_writable)?;
+    let block = [0; SPARSE_BLOCK];
+    let mut remaining = size;
+    let result = loop {
+        if remaining == 0 {
+            break file.sync_all();
+        }
+        let n = remaining.min(block.len());
+        if let Err(e) = file.write_all(&block[..n]) {
+            break Err(e);
+        }
+        remaining -= n;
+    };
+    drop(file);
+    let removed = fs::remove_file(&probe);
+
+    result.and(removed).map_err(not_writable)
+}
+
+#[cfg(unix)]
+fn set_writable(permissions: &mut fs::Permissions) {
+    use std::os::unix::fs::PermissionsExt;
+
+    permissions.set_mode(permissions.mode() | 0o200);
+}
+
+#[cfg(not(unix))]
+#[allow(clippy::permissions_set_readonly_false)]
+fn set_writable(permissions: &mut fs::Permissions) {
+    permissions.set_readonly(false);
+}
+
+/// The metadata key holding the holes of a sparse file, as
+/// comma-separated `offset+length` byte ranges in ascending order.
+pub(crate) const SPARSE: &str = "sparse";
+
+fn parse_holes(holes: &str) -> Option<Vec<(u64, u64)>> {
+    let mut end = 0;
+    holes
+        .split(',')
+        .map(|hole| {
+            let (offset, len) = hole.split_once('+')?;
+            let (offset, len) = (offset.parse::<u64>().ok()?, len.parse::<u64>().ok()?);
+            if offset < end || i64::try_from(len).is_err() {
+                return None;
+            }
+            end = offset.checked_add(len)?;
+            Some((offset, len))
+        })
+        .collect()
+}
+
+/// Writes `data` with the given holes put back in, seeking over the holes
+/// and blocks of NUL bytes if `sparse` is set and writing NUL bytes
+/// otherwise.
+fn write_data(
+    file: &mut fs::File,
+    data: &[u8],
+    holes: &[(u64, u64)],
+    sparse: bool,
+) -> io::Result<()> {
+    let mut w = BufWriter::new(file);
+    let write = |w: &mut BufWriter<_>, data: &[u8]| {
+        if sparse {
+            write_sparse(w, data)
+        } else {
+            w.write_all(data)
+        }
+    };
+
+    let (mut rest, mut pos) = (data, 0);
+    for &(offset, len) in holes {
+        let n = usize::try_from(offset - pos).unwrap_or(usize::MAX);
+        if n > rest.len() {
+            let msg = "sparse metadata does not match the file";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+        let (before, after) = rest.split_at(n);
+        write(&mut w, before)?;
+        if sparse {
+            w.seek(SeekFrom::Current(len as i64))?;
+        } else {
+            io::copy(&mut io::repeat(0).take(len), &mut w)?;
+        }
+        (rest, pos) = (after, offset + len);
+    }
+    write(&mut w, rest)?;
+
+    let file = w.into_inner().map_err(io::IntoInnerError::into_error)?;
+    file.set_len(pos + rest.len() as u64)
+}
+
+fn write_sparse<W: Write + Seek>(w: &mut W, data: &[u8]) -> io::Result<()> {
+    for block in data.chunks(SPARSE_BLOCK) {
+        if block.iter().all(|&b| b == 0) {
+            w.seek(SeekFrom::Current(block.len() as i64))?;
+        } else {
+            w.write_all(block)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::{prelude::*, TempDir};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn materialize_sparse() {
+        let dir = TempDir::new().unwrap();
+        let zeros = "\0".repeat(3 * SPARSE_BLOCK + 10);
+        let txt = format!("-- image --\nhead{zeros}tail\n-- zeros --\n{zeros}");
+        let archive = Archive::from(txt.as_str());
+
+        archive
+            .materialize_with(&dir, &MaterializeOptions::new().sparse(true))
+            .unwrap();
+
+        let image = fs::read_to_string(dir.child("image").path()).unwrap();
+        assert_eq!(image, format!("head{zeros}tail\n"));
+        let zeros_file = fs::read_to_string(dir.child("zeros").path()).unwrap();
+        assert_eq!(zeros_file, format!("{zeros}\n"));
+    }
+
+    #[test]
+    fn materialize_cleanup() {
+        let dir = TempDir::new().unwrap();
+        dir.child("shared/keep").touch().unwrap();
+        let root = dir.child("root");
+        let archive = Archive::from("-- a --\n-- b/c/d --\n-- shared/e --\n");
+
+        let manifest = archive
+            .materialize_with(&root, &MaterializeOptions::new())
+            .unwrap();
+        assert_eq!(
+            manifest.files(),
+            ["a", "b/c/d", "shared/e"].map(PathBuf::from)
+        );
+        assert_eq!(
+            manifest.dirs(),
+            ["", "b", "b/c", "shared"].map(PathBuf::from)
+        );
+
+        let manifest = archive
+            .materialize_with(&dir, &MaterializeOptions::new())
+            .unwrap();
+        assert_eq!(manifest.dirs(), ["b", "b/c"].map(PathBuf::from));
+        dir.child("b/mine").touch().unwrap();
+
+        cleanup(&dir, &manifest).unwrap();
+        dir.child("a").assert(predicates::path::missing());
+        dir.child("b/c").assert(predicates::path::missing());
+        dir.child("b/mine").assert(predicates::path::exists());
+        dir.child("shared/e").assert(predicates::path::missing());
+        dir.child("shared/keep").assert(predicates::path::exists());
+        root.child("a").assert(predicates::path::exists());
+    }
+
+    #[test]
+    fn materialize_conditions() {
+        let dir = TempDir::new().unwrap();
+        let mut archive = Archive::from("-- all --\n-- this --\n-- other --\n-- not --\n");
+        let mut condition = |i: usize, value: String| {
+            archive.files[i].metadata_mut().insert("if".into(), value);
+        };
+        condition(1, format!("plan9,{}", env::consts::OS));
+        condition(2, "plan9".into());
+        condition(3, format!("!{}", env::consts::FAMILY));
+
+        let manifest = archive
+            .materialize_with(&dir, &MaterializeOptions::new())
+            .unwrap();
+        assert_eq!(manifest.files(), ["all", "this"].map(PathBuf::from));
+
+        let options = MaterializeOptions::new().conditions(false);
+        let manifest = archive.materialize_with(dir.child("x"), &options).unwrap();
+        assert_eq!(manifest.files().len(), 4);
+    }
+
+    #[test]
+    fn materialize_read_only() {
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::from("-- a --\nhello\n-- b/c --\nworld\n");
+        let read_only = |name| {
+            let metadata = fs::metadata(dir.child(name).path()).unwrap();
+            metadata.permissions().readonly()
+        };
+
+        let options = MaterializeOptions::new().read_only(true);
+        let manifest = archive.materialize_with(&dir, &options).unwrap();
+        assert!(read_only("a"));
+        assert!(read_only("b/c"));
+        assert!(!read_only("b"));
+
+        fs::remove_file(dir.child("a").path()).unwrap();
+        restore_writable(&dir, &manifest).unwrap();
+        assert!(!read_only("b/c"));
+
+        let outside = dir.child("outside");
+        outside.write_str("x").unwrap();
+        let mut permissions = fs::metadata(&outside).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&outside, permissions).unwrap();
+        let escaping = Archive::from("-- ../outside --\n-- b/../../outside --\n");
+        escaping.restore_writable(dir.child("b"), &options).unwrap();
+        assert!(read_only("outside"));
+
+        let nested = |name: &Path| Ok(Path::new("nested").join(ContainedPaths.check(name)?));
+        let options = options.path_policy(nested);
+        let dir = TempDir::new().unwrap();
+        archive.materialize_with(&dir, &options).unwrap();
+        archive.restore_writable(&dir, &options).unwrap();
+        let metadata = fs::metadata(dir.child("nested/b/c").path()).unwrap();
+        assert!(!metadata.permissions().readonly());
+    }
+
+    #[test]
+    fn materialize_split() {
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::from(
+            "-- input/a --\n1\n-- input/golden/b --\n2\n-- expected/a --\n3\n-- README --\n",
+        );
+        let roots = vec![
+            ("input", dir.child("work").to_path_buf()),
+            ("input/golden/", dir.child("golden").to_path_buf()),
+            ("./expected", dir.child("golden").to_path_buf()),
+        ];
+        let err = archive
+            .materialize_split(roots.clone(), &MaterializeOptions::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "README: no target directory for file");
+        dir.child("work").assert(predicates::path::missing());
+
+        let rest = ("", dir.child("rest").to_path_buf());
+        let manifests = archive
+            .materialize_split([rest].into_iter().chain(roots), &MaterializeOptions::new())
+            .unwrap();
+        let files: Vec<_> = manifests.iter().map(Manifest::files).collect();
+        assert_eq!(
+            files,
+            [&["README"], &["a"], &["b"], &["a"]].map(|f| f.map(PathBuf::from).to_vec())
+        );
+        dir.child("work/a").assert("1\n");
+        dir.child("golden/b").assert("2\n");
+        dir.child("golden/a").assert("3\n");
+    }
+
+    #[test]
+    fn materialize_mtimes() {
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::from("-- a --\n-- b/c --\nx\n-- d --\n");
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let options = MaterializeOptions::new().mtimes(base).read_only(true);
+        archive.materialize_with(&dir, &options).unwrap();
+
+        let mtimes = ["a", "b/c", "d"].map(|name| {
+            let metadata = fs::metadata(dir.child(name).path()).unwrap();
+            metadata.modified().unwrap().duration_since(base).unwrap()
+        });
+        assert_eq!(mtimes, [0, 1, 2].map(Duration::from_secs));
+        archive.restore_writable(&dir, &options).unwrap();
+    }
+
+    #[test]
+    fn materialize_path_policy() {
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::from("-- a --\n-- src/b --\n-- ../c --\n");
+        let flatten = |name: &Path| match name.file_name() {
+            Some(file_name) => Ok(PathBuf::from(file_name)),
+            None => Err(MaterializeError::Rejected(
+                name.to_string_lossy().to_string(),
+                "no file name",
+            )),
+        };
+
+        let err = archive
+            .materialize_with(&dir, &MaterializeOptions::new())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "../c: outside parent directory");
+
+        let options = MaterializeOptions::new().path_policy(flatten);
+        let manifest = archive.materialize_with(&dir, &options).unwrap();
+        assert_eq!(manifest.files(), ["a", "b", "c"].map(PathBuf::from));
+
+        let archive = Archive::from("-- x/.. --\n");
+        let err = archive.materialize_with(&dir, &options).unwrap_err();
+        assert_eq!(err.to_string(), "x/..: no file name");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn materialize_preflight() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::from("-- a --\n-- b/c --\nhello\n");
+        let options = MaterializeOptions::new().preflight(true);
+        archive
+            .materialize_with(dir.child("out"), &options)
+            .unwrap();
+        assert_eq!(fs::read_dir(dir.child("out")).unwrap().count(), 2);
+
+        let locked = dir.child("locked");
+        locked.create_dir_all().unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+        if fs::write(locked.child("root").path(), "").is_ok() {
+            // Permissions are not enforced, e.g. when running as root.
+            return;
+        }
+        let err = archive
+            .materialize_with(locked.child("out"), &options)
+            .unwrap_err();
+        assert!(matches!(
+            &err,
+            MaterializeError::NotWritable(_, e) if e.kind() == io::ErrorKind::PermissionDenied
+        ));
+        assert!(fs::read_dir(&locked).unwrap().next().is_none());
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn materialize_preflight_parallel() {
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::from(format!("-- a --\n{}\n", "x".repeat(1 << 16)).as_str());
+        let options = MaterializeOptions::new().preflight(true);
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let (archive, options, out) = (&archive, &options, dir.child(format!("out{i}")));
+                s.spawn(move || archive.materialize_with(out, options).unwrap());
+            }
+        });
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 8);
+    }
+
+    #[test]
+    fn materialize_case_insensitive() {
+        let dir = TempDir::new().unwrap();
+        let archive = Archive::from("-- README --\n-- src/a --\n-- Src/A --\n");
+        let options = MaterializeOptions::new().case_insensitive(true);
+
+        let err = archive.materialize_with(&dir, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "src/a: conflicts with Src/A on case-insensitive filesystems"
+        );
+        dir.child("README").assert(predicates::path::missing());
+
+        let roots = [
+            ("", dir.to_path_buf()),
+            ("src", dir.child("lower").to_path_buf()),
+            ("Src", dir.child("upper").to_path_buf()),
+        ];
+        let manifests = archive.materialize_split(roots, &options).unwrap();
+        assert_eq!(manifests.len(), 3);
+    }
+}
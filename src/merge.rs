@@ -0,0 +1,147 @@
+use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+use crate::{Archive, DuplicateError};
+
+/// How [`Archive::merge`] resolves files of the right archive whose name
+/// is already taken.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum MergeStrategy {
+    /// Keep the existing file and drop the new one.
+    KeepLeft,
+    /// Replace the existing file with the new one, in place.
+    KeepRight,
+    /// Fail with a [`DuplicateError`] on the first collision.
+    Error,
+    /// Add the new file under its name with the given suffix appended,
+    /// followed by a number if that name is taken too.
+    RenameWithSuffix(String),
+}
+
+impl Archive {
+    /// Merges the files of `other` into this archive, resolving name
+    /// collisions according to the strategy. Files with new names are
+    /// appended in order. The comment of this archive is kept; see
+    /// [`Archive::append`] to combine comments.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use txtar::{Archive, MergeStrategy};
+    ///
+    /// let mut fixture = Archive::from("-- config --\ndefault\n-- data --\n1\n");
+    /// let overrides = Archive::from("-- config --\ncustom\n-- extra --\n");
+    /// fixture.merge(overrides, &MergeStrategy::KeepRight).unwrap();
+    /// assert_eq!(
+    ///     fixture.to_string(),
+    ///     "-- config --\ncustom\n-- data --\n1\n-- extra --\n"
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// With [`MergeStrategy::Error`], this function errors on the first
+    /// collision and leaves the archive unchanged.
+    pub fn merge(
+        &mut self,
+        other: Archive,
+        strategy: &MergeStrategy,
+    ) -> Result<(), DuplicateError> {
+        let mut positions: HashMap<PathBuf, usize> = HashMap::new();
+        for (i, file) in self.files.iter().enumerate() {
+            positions.entry(file.name.clone()).or_insert(i);
+        }
+        if *strategy == MergeStrategy::Error {
+            let mut seen = HashMap::new();
+            for file in &other.files {
+                let first = match positions.get(&file.name) {
+                    Some(&i) => Some(&self.files[i]),
+                    None => seen.insert(&file.name, file),
+                };
+                if let Some(first) = first {
+                    return Err(DuplicateError::new(first, file));
+                }
+            }
+        }
+
+        for mut file in other.files {
+            let Some(&i) = positions.get(&file.name) else {
+                positions.insert(file.name.clone(), self.files.len());
+                self.files.push(file);
+                continue;
+            };
+            match strategy {
+                MergeStrategy::KeepLeft | MergeStrategy::Error => {}
+                MergeStrategy::KeepRight => self.files[i] = file,
+                MergeStrategy::RenameWithSuffix(suffix) => {
+                    let name: OsString = [file.name.as_os_str(), suffix.as_ref()]
+                        .into_iter()
+                        .collect();
+                    let mut candidate = PathBuf::from(&name);
+                    let mut n = 2;
+                    while positions.contains_key(&candidate) {
+                        let mut numbered = name.clone();
+                        numbered.push(n.to_string());
+                        candidate = numbered.into();
+                        n += 1;
+                    }
+                    file.name = candidate;
+                    positions.insert(file.name.clone(), self.files.len());
+                    self.files.push(file);
+                }
+            }
+        }
+        self.reindex();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+    use std::path::Path;
+
+    #[test]
+    fn merge() {
+        let left = || Archive::from("base\n-- a --\n1\n-- b --\n2\n-- a.new --\n");
+        let right = || Archive::from("over\n-- a --\n3\n-- c --\n-- a --\n4\n");
+        let merge = |strategy| {
+            let mut archive = left();
+            archive.build_index();
+            archive.merge(right(), &strategy).map(|()| archive)
+        };
+
+        let archive = merge(MergeStrategy::KeepLeft).unwrap();
+        assert_eq!(
+            archive.to_string(),
+            "base\n-- a --\n1\n-- b --\n2\n-- a.new --\n-- c --\n"
+        );
+        let archive = merge(MergeStrategy::KeepRight).unwrap();
+        assert_eq!(
+            archive.to_string(),
+            "base\n-- a --\n4\n-- b --\n2\n-- a.new --\n-- c --\n"
+        );
+        let archive = merge(MergeStrategy::RenameWithSuffix(".new".into())).unwrap();
+        assert_eq!(
+            archive.to_string(),
+            "base\n-- a --\n1\n-- b --\n2\n-- a.new --\n-- a.new2 --\n3\n-- c --\n-- a.new3 --\n4\n"
+        );
+        assert_eq!(archive.get("a.new2").unwrap().data(), "3\n");
+
+        let err = merge(MergeStrategy::Error).unwrap_err();
+        assert_eq!(err.to_string(), "a: duplicate file name");
+        let mut archive = left();
+        archive.merge(right(), &MergeStrategy::Error).unwrap_err();
+        assert_eq!(archive, left());
+
+        let mut right = right();
+        right.set_origin("right.txtar");
+        let err = archive.merge(right, &MergeStrategy::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "a: duplicate file name (from unknown and right.txtar)"
+        );
+        assert_eq!(err.second.as_deref(), Some(Path::new("right.txtar")));
+    }
+}
@@ -0,0 +1,125 @@
+use std::{collections::BTreeMap, env, fmt};
+
+use crate::Archive;
+
+/**
+Information about the environment an archive was created in.
+
+It is stored as `key: value` lines in the [`Meta::PATH`] member, with
+environment variables sorted by name, so capturing the same environment
+twice produces the same member.
+
+# Examples
+
+```rust no_run
+use txtar::{Archive, PackOptions};
+
+let options = PackOptions::new().meta(["RUST_LOG"]);
+let (archive, _) = Archive::from_dir_with("repro/", &options).unwrap();
+
+let meta = archive.meta().unwrap();
+println!("packed on {} with RUST_LOG={:?}", meta.os, meta.env.get("RUST_LOG"));
+```
+**/
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Meta {
+    /// The version of this crate.
+    pub version: String,
+    /// The operating system, as in [`std::env::consts::OS`].
+    pub os: String,
+    /// The CPU architecture, as in [`std::env::consts::ARCH`].
+    pub arch: String,
+    /// The captured environment variables that were set.
+    pub env: BTreeMap<String, String>,
+}
+
+impl Meta {
+    /// The name of the archive member holding the metadata.
+    pub const PATH: &'static str = ".txtar/meta";
+
+    /// Captures the current platform and the given environment
+    /// variables. Variables that are not set, not valid unicode or span
+    /// multiple lines are left out.
+    pub fn capture<I, S>(vars: I) -> Meta
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let env = vars
+            .into_iter()
+            .filter_map(|var| {
+                let var = var.as_ref();
+                let value = env::var(var).ok().filter(|v| !v.contains('\n'))?;
+                Some((var.to_owned(), value))
+            })
+            .collect();
+
+        Meta {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            os: env::consts::OS.to_owned(),
+            arch: env::consts::ARCH.to_owned(),
+            env,
+        }
+    }
+
+    /// Parses metadata from the contents of a [`Meta::PATH`] member.
+    /// Unknown keys are ignored.
+    pub fn parse(s: &str) -> Meta {
+        let mut meta = Meta::default();
+        for (key, value) in s.lines().filter_map(|l| l.split_once(": ")) {
+            match key {
+                "version" => meta.version = value.to_owned(),
+                "os" => meta.os = value.to_owned(),
+                "arch" => meta.arch = value.to_owned(),
+                _ => {
+                    if let Some(var) = key.strip_prefix("env.") {
+                        meta.env.insert(var.to_owned(), value.to_owned());
+                    }
+                }
+            }
+        }
+
+        meta
+    }
+}
+
+impl fmt::Display for Meta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version: {}", self.version)?;
+        writeln!(f, "os: {}", self.os)?;
+        writeln!(f, "arch: {}", self.arch)?;
+        for (var, value) in &self.env {
+            writeln!(f, "env.{var}: {value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Archive {
+    /// Returns the metadata stored in the [`Meta::PATH`] member, if any.
+    pub fn meta(&self) -> Option<Meta> {
+        self.files
+            .iter()
+            .find(|f| f.name.as_os_str() == Meta::PATH)
+            .map(|f| Meta::parse(&f.data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn meta_roundtrip() {
+        let meta = Meta::capture(["PATH", "TXTAR_SURELY_UNSET"]);
+        assert_eq!(meta.version, env!("CARGO_PKG_VERSION"));
+        assert!(meta.env.contains_key("PATH"));
+        assert!(!meta.env.contains_key("TXTAR_SURELY_UNSET"));
+
+        let archive = Archive::from(format!("-- {} --\n{meta}", Meta::PATH).as_str());
+        assert_eq!(archive.meta(), Some(meta));
+        assert_eq!(Archive::from("-- a --\n").meta(), None);
+    }
+}
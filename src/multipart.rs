@@ -0,0 +1,242 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use clean_path::Clean;
+
+use crate::{Archive, File, MultipartError};
+
+const CRLF: &str = "\r\n";
+
+impl Archive {
+    /// Serialize the archive as a `multipart/form-data` body into the I/O
+    /// stream, using the given boundary.
+    ///
+    /// The comment is sent as a part named `comment`, followed by one part
+    /// named `file` per file, with the file name as its `filename`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the boundary occurs in the comment or
+    /// in any file. Additionally, any errors caused by the underlying I/O
+    /// operations will be propagated.
+    pub fn to_multipart<W: Write>(&self, writer: &mut W, boundary: &str) -> io::Result<()> {
+        let delimiter = format!("--{boundary}");
+        let texts = std::iter::once(&self.comment).chain(self.files.iter().map(|f| &f.data));
+        for text in texts {
+            if text.contains(&delimiter) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "multipart boundary occurs in archive",
+                ));
+            }
+        }
+
+        write!(writer, "{delimiter}{CRLF}")?;
+        write!(
+            writer,
+            "Content-Disposition: form-data; name=\"comment\"{CRLF}{CRLF}"
+        )?;
+        write!(writer, "{}{CRLF}", self.comment)?;
+
+        for File { name, data, .. } in &self.files {
+            let filename = escape(&name.to_string_lossy());
+            write!(writer, "{delimiter}{CRLF}")?;
+            write!(
+                writer,
+                "Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"{CRLF}"
+            )?;
+            write!(
+                writer,
+                "Content-Type: text/plain; charset=utf-8{CRLF}{CRLF}"
+            )?;
+            write!(writer, "{data}{CRLF}")?;
+        }
+
+        write!(writer, "{delimiter}--{CRLF}")
+    }
+
+    /// Reads an archive from a `multipart/form-data` body with the given
+    /// boundary, as produced by [`Archive::to_multipart`] or an HTML form.
+    ///
+    /// Every part with a `filename` becomes a file. A part named
+    /// `comment` without a `filename` becomes the comment, and all other
+    /// parts are ignored.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if the body is malformed, if a part is
+    /// not valid UTF-8, or if a file name would refer to a path outside
+    /// of the archive root.
+    pub fn from_multipart(body: &[u8], boundary: &str) -> Result<Archive, MultipartError> {
+        let delimiter = format!("{CRLF}--{boundary}");
+        // The first delimiter is not required to follow a line break.
+        let mut rest = body
+            .strip_prefix(&delimiter.as_bytes()[CRLF.len()..])
+            .or_else(|| find(body, delimiter.as_bytes()).map(|i| &body[i + delimiter.len()..]))
+            .ok_or(MultipartError::Malformed("missing opening boundary"))?;
+
+        let mut comment = String::new();
+        let mut files = Vec::new();
+        loop {
+            if rest.starts_with(b"--") {
+                break;
+            }
+            rest = rest
+                .strip_prefix(CRLF.as_bytes())
+                .ok_or(MultipartError::Malformed(
+                    "missing line break after boundary",
+                ))?;
+
+            let end = find(rest, delimiter.as_bytes())
+                .ok_or(MultipartError::Malformed("missing closing boundary"))?;
+            let (part, next) = rest.split_at(end);
+            rest = &next[delimiter.len()..];
+
+            let header_end = find(part, b"\r\n\r\n")
+                .ok_or(MultipartError::Malformed("missing end of part headers"))?;
+            let headers = std::str::from_utf8(&part[..header_end])
+                .map_err(|_| MultipartError::Malformed("part headers are not UTF-8"))?;
+            let content = &part[header_end + 4..];
+
+            let disposition = headers
+                .split(CRLF)
+                .find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("content-disposition")
+                        .then_some(value)
+                })
+                .ok_or(MultipartError::Malformed("missing Content-Disposition"))?;
+
+            let name = param(disposition, "name");
+            match param(disposition, "filename") {
+                Some(filename) => {
+                    let data = std::str::from_utf8(content)
+                        .map_err(|_| MultipartError::Utf8(filename.clone()))?;
+                    let path = Path::new(&filename).clean();
+                    if path.starts_with("../") || path.is_absolute() {
+                        return Err(MultipartError::DirEscape(filename));
+                    }
+                    files.push(File::new(filename, data));
+                }
+                None if name.as_deref() == Some("comment") => {
+                    comment = std::str::from_utf8(content)
+                        .map_err(|_| MultipartError::Utf8("comment".to_owned()))?
+                        .to_owned();
+                }
+                None => {}
+            }
+        }
+
+        Ok(Archive::from_parts(&comment, files))
+    }
+}
+
+/// Escapes a file name for use in a quoted header parameter, the way
+/// browsers do for form submissions.
+fn escape(s: &str) -> String {
+    s.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("%22", "\"")
+        .replace("%0D", "\r")
+        .replace("%0A", "\n")
+}
+
+/// Returns the value of a parameter of a `Content-Disposition` header.
+fn param(header: &str, key: &str) -> Option<String> {
+    let mut in_quotes = false;
+    let params = header.split(|c| {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        c == ';' && !in_quotes
+    });
+
+    params.skip(1).find_map(|p| {
+        let (k, v) = p.split_once('=')?;
+        if !k.trim().eq_ignore_ascii_case(key) {
+            return None;
+        }
+        let v = v.trim();
+        let v = v
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(v);
+        Some(unescape(v))
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn multipart_roundtrip() {
+        let archive =
+            Archive::from("comment\n-- a.txt --\nhello\n-- dir/\"q\";.txt --\n-- b --\nb");
+        let mut body = Vec::new();
+        archive.to_multipart(&mut body, "XyZ").unwrap();
+
+        let expected = concat!(
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"comment\"\r\n\r\n",
+            "comment\n\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n\r\n",
+            "hello\n\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"file\"; filename=\"dir/%22q%22;.txt\"\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n\r\n",
+            "\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"file\"; filename=\"b\"\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n\r\n",
+            "b\n\r\n",
+            "--XyZ--\r\n",
+        );
+        assert_eq!(String::from_utf8(body.clone()).unwrap(), expected);
+        assert_eq!(Archive::from_multipart(&body, "XyZ").unwrap(), archive);
+
+        let mut body = Vec::new();
+        assert!(archive.to_multipart(&mut body, "hello").is_ok());
+        assert!(Archive::from("--a\n").to_multipart(&mut body, "a").is_err());
+    }
+
+    #[test]
+    fn multipart_checks() {
+        let part = |filename: &str, data: &[u8]| {
+            let mut body = format!(
+                "--b\r\nContent-Disposition: form-data; name=\"f\"; filename=\"{filename}\"\r\n\r\n"
+            )
+            .into_bytes();
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n--b--\r\n");
+            body
+        };
+
+        match Archive::from_multipart(&part("../x", b"x"), "b") {
+            Err(MultipartError::DirEscape(name)) => assert_eq!(name, "../x"),
+            other => panic!("expected `MultipartError::DirEscape`, got {:?}", other),
+        }
+        match Archive::from_multipart(&part("x", b"\xff"), "b") {
+            Err(MultipartError::Utf8(name)) => assert_eq!(name, "x"),
+            other => panic!("expected `MultipartError::Utf8`, got {:?}", other),
+        }
+        match Archive::from_multipart(b"--b\r\nno headers", "b") {
+            Err(MultipartError::Malformed(_)) => {}
+            other => panic!("expected `MultipartError::Malformed`, got {:?}", other),
+        }
+    }
+}
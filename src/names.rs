@@ -0,0 +1,115 @@
+use std::{fmt, path::Path, sync::Arc};
+
+type Allowed = dyn Fn(char) -> bool + Send + Sync;
+
+/**
+Rules that file names must follow, to enforce naming conventions.
+
+They are checked when parsing with [`ParseOptions::name_rules`], building
+with [`ArchiveBuilder::name_rules`] and materializing with
+[`MaterializeOptions::name_rules`].
+
+# Examples
+
+```rust
+use txtar::{Archive, NameRules, ParseOptions};
+
+let rules = NameRules::new()
+    .max_len(64)
+    .allowed_chars(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "._-/".contains(c));
+let options = ParseOptions::new().name_rules(rules);
+
+assert!(Archive::parse_with("-- src/main.rs --\n", &options).is_ok());
+let err = Archive::parse_with("-- My File.txt --\n", &options).unwrap_err();
+assert_eq!(err.to_string(), "My File.txt: disallowed character in file name");
+```
+
+[`ParseOptions::name_rules`]: crate::ParseOptions::name_rules
+[`ArchiveBuilder::name_rules`]: crate::ArchiveBuilder::name_rules
+[`MaterializeOptions::name_rules`]: crate::MaterializeOptions::name_rules
+**/
+#[derive(Default, Clone)]
+pub struct NameRules {
+    max_len: Option<usize>,
+    allowed: Option<Arc<Allowed>>,
+}
+
+impl NameRules {
+    pub fn new() -> NameRules {
+        NameRules::default()
+    }
+
+    /// Sets the maximum length of names in bytes.
+    pub fn max_len(mut self, max_len: usize) -> NameRules {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// Sets a function that decides which characters names may contain.
+    pub fn allowed_chars<F>(mut self, allowed: F) -> NameRules
+    where
+        F: Fn(char) -> bool + Send + Sync + 'static,
+    {
+        self.allowed = Some(Arc::new(allowed));
+        self
+    }
+
+    /// Checks the name against the rules, returning the reason it breaks
+    /// them if it does.
+    pub fn check(&self, name: &Path) -> Result<(), &'static str> {
+        if self.max_len.is_some_and(|max| name.as_os_str().len() > max) {
+            return Err("file name too long");
+        }
+        if let Some(allowed) = &self.allowed {
+            let name = name.to_string_lossy();
+            if !name.chars().all(|c| allowed(c)) {
+                return Err("disallowed character in file name");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for NameRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NameRules")
+            .field("max_len", &self.max_len)
+            .field("allowed", &self.allowed.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Archive, ArchiveBuilder, MaterializeOptions};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn name_rules() {
+        let rules = NameRules::new()
+            .max_len(5)
+            .allowed_chars(|c| c.is_ascii_alphanumeric() || c == '/');
+        assert_eq!(rules.check(Path::new("a/b")), Ok(()));
+        assert_eq!(rules.check(Path::new("abcdef")), Err("file name too long"));
+        assert_eq!(
+            rules.check(Path::new("a b")),
+            Err("disallowed character in file name")
+        );
+        assert_eq!(NameRules::new().check(Path::new("any name")), Ok(()));
+
+        let err = ArchiveBuilder::new()
+            .name_rules(rules.clone())
+            .file("ok", "")
+            .file("not ok", "")
+            .build()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "not ok: file name too long");
+
+        let archive = Archive::from("-- a --\n-- b.txt --\n");
+        let options = MaterializeOptions::new().name_rules(rules);
+        let err = archive.materialize_with("unused", &options).unwrap_err();
+        assert_eq!(err.to_string(), "b.txt: disallowed character in file name");
+    }
+}
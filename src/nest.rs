@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+
+use crate::Archive;
+
+impl Archive {
+    /// Moves every file into the directory `prefix` by prepending it to
+    /// the file names.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use txtar::Archive;
+    ///
+    /// let mut fixture = Archive::from("-- go.mod --\n-- main.go --\n");
+    /// fixture.nest_under("testdata/mod");
+    /// assert_eq!(
+    ///     fixture.to_string(),
+    ///     "-- testdata/mod/go.mod --\n-- testdata/mod/main.go --\n"
+    /// );
+    /// ```
+    pub fn nest_under<P: AsRef<Path>>(&mut self, prefix: P) {
+        let prefix = prefix.as_ref();
+        for file in &mut self.files {
+            file.name = prefix.join(&file.name);
+        }
+        self.reindex();
+    }
+
+    /// Keeps only the files below the directory `prefix` and removes the
+    /// prefix from their names. Prefixes are compared component by
+    /// component, as in [`Path::strip_prefix`].
+    pub fn strip_prefix<P: AsRef<Path>>(&mut self, prefix: P) {
+        let prefix = prefix.as_ref();
+        self.rename_or_drop(|name| name.strip_prefix(prefix).ok().map(Path::to_owned));
+    }
+
+    /// Removes the first `n` components from the names of the files, like
+    /// `tar --strip-components`. Files whose names have no more than `n`
+    /// components are dropped.
+    pub fn strip_components(&mut self, n: usize) {
+        self.rename_or_drop(|name| {
+            let mut components = name.components();
+            for _ in 0..n {
+                components.next()?;
+            }
+            Some(components.as_path().to_owned())
+        });
+    }
+
+    /// Renames each file to the name returned by the function, dropping
+    /// files for which it returns `None` or an empty name.
+    fn rename_or_drop<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Path) -> Option<PathBuf>,
+    {
+        self.files.retain_mut(|file| match f(&file.name) {
+            Some(name) if !name.as_os_str().is_empty() => {
+                file.name = name;
+                true
+            }
+            _ => false,
+        });
+        self.reindex();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn nest_and_strip() {
+        let mut archive = Archive::from("c\n-- a/b/x --\n1\n-- a/y --\n-- ab/z --\n-- a --\n");
+        archive.build_index();
+
+        archive.strip_prefix("a");
+        assert_eq!(archive.to_string(), "c\n-- b/x --\n1\n-- y --\n");
+        archive.nest_under("root/dir");
+        assert_eq!(
+            archive.to_string(),
+            "c\n-- root/dir/b/x --\n1\n-- root/dir/y --\n"
+        );
+        archive.strip_components(2);
+        assert_eq!(archive.to_string(), "c\n-- b/x --\n1\n-- y --\n");
+        archive.strip_components(1);
+        assert_eq!(archive.to_string(), "c\n-- x --\n1\n");
+        assert!(archive.contains("x"));
+
+        archive.strip_components(0);
+        assert_eq!(archive.to_string(), "c\n-- x --\n1\n");
+        archive.strip_components(1);
+        assert_eq!(archive.to_string(), "c\n");
+    }
+}
@@ -0,0 +1,116 @@
+use std::{cmp, path::Path};
+
+use crate::{Archive, File};
+
+/// An order for the files of an archive.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Ordering {
+    /// Keep the order in which the files were added.
+    #[default]
+    Original,
+    /// Sort by name, comparing path components.
+    Lexicographic,
+    /// Sort by name, but list the contents of subdirectories before the
+    /// files of each directory.
+    DirectoriesFirst,
+}
+
+impl Ordering {
+    pub(crate) fn compare(self, a: &Path, b: &Path) -> cmp::Ordering {
+        match self {
+            Ordering::Original => cmp::Ordering::Equal,
+            Ordering::Lexicographic => a.cmp(b),
+            Ordering::DirectoriesFirst => {
+                let mut a = a.components().peekable();
+                let mut b = b.components().peekable();
+                loop {
+                    match (a.next(), b.next()) {
+                        (Some(x), Some(y)) => {
+                            let a_is_dir = a.peek().is_some();
+                            let b_is_dir = b.peek().is_some();
+                            let order = b_is_dir.cmp(&a_is_dir).then_with(|| x.cmp(&y));
+                            if order.is_ne() {
+                                return order;
+                            }
+                        }
+                        (x, y) => return x.is_some().cmp(&y.is_some()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Archive {
+    /// Sorts the files according to the given ordering. The sort is
+    /// stable, so files with the same name keep their relative order.
+    pub fn sort(&mut self, ordering: Ordering) {
+        if ordering != Ordering::Original {
+            self.files
+                .sort_by(|a, b| ordering.compare(&a.name, &b.name));
+            self.reindex();
+        }
+    }
+
+    /// Sorts the files by name. The sort is stable.
+    #[doc(alias = "sort_files")]
+    pub fn sort_by_name(&mut self) {
+        self.sort(Ordering::Lexicographic);
+    }
+
+    /// Sorts the files by name with a comparator function. The sort is
+    /// stable.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&Path, &Path) -> cmp::Ordering,
+    {
+        self.files
+            .sort_by(|a: &File, b: &File| compare(&a.name, &b.name));
+        self.reindex();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    fn names(archive: &Archive) -> Vec<String> {
+        archive
+            .files
+            .iter()
+            .map(|f| f.name.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn sort_orderings() {
+        let txt = "-- b.txt --\n-- a/z --\n-- a.txt --\n-- a/b/c --\n-- a --\n-- b.txt --\n2\n";
+        let mut archive = Archive::from(txt);
+
+        archive.sort(Ordering::Original);
+        assert_eq!(
+            names(&archive),
+            ["b.txt", "a/z", "a.txt", "a/b/c", "a", "b.txt"]
+        );
+
+        archive.sort(Ordering::DirectoriesFirst);
+        assert_eq!(
+            names(&archive),
+            ["a/b/c", "a/z", "a", "a.txt", "b.txt", "b.txt"]
+        );
+
+        archive.sort_by_name();
+        assert_eq!(
+            names(&archive),
+            ["a", "a/b/c", "a/z", "a.txt", "b.txt", "b.txt"]
+        );
+        assert_eq!(archive.files[5].data, "2\n");
+
+        archive.sort_by(|a, b| b.cmp(a));
+        assert_eq!(
+            names(&archive),
+            ["b.txt", "b.txt", "a.txt", "a/z", "a/b/c", "a"]
+        );
+    }
+}
@@ -0,0 +1,583 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "encoding")]
+use crate::encoding;
+use crate::{
+    materialize::{SPARSE, SPARSE_BLOCK},
+    Archive, File, Meta, Ordering, PackError,
+};
+
+/// What to do with files that cannot be stored as text.
+///
+/// A file is considered binary if it contains NUL bytes or is not valid
+/// UTF-8.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum BinaryPolicy {
+    /// Leave the file out of the archive and list it in the report.
+    #[default]
+    Skip,
+    /// Abort packing with [`PackError::Binary`].
+    Error,
+    /// Convert UTF-16 files to UTF-8 and skip the remaining binary
+    /// files, see [`encoding::to_utf8`].
+    #[cfg(feature = "encoding")]
+    Transcode,
+    /// Like [`BinaryPolicy::Transcode`], but also read files without
+    /// control characters as Latin-1, see
+    /// [`encoding::to_utf8_or_latin1`]. Binary files that happen to
+    /// contain no control characters are stored as garbled text.
+    #[cfg(feature = "encoding")]
+    TranscodeLatin1,
+}
+
+/// What to do with sockets, FIFOs, device nodes and other files that are
+/// neither regular files nor directories.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum SpecialFilePolicy {
+    /// Leave the file out of the archive and list it in the report.
+    #[default]
+    Skip,
+    /// Abort packing with [`PackError::SpecialFile`].
+    Error,
+}
+
+/// What to do with files and directories that cannot be read, such as
+/// those without read permission or broken symbolic links.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum ReadErrorPolicy {
+    /// Abort packing with [`PackError::Io`].
+    #[default]
+    Error,
+    /// Leave the path out of the archive and list it in the report.
+    Skip,
+}
+
+type Transform<'a> = dyn Fn(&Path, String) -> String + 'a;
+
+/**
+Options controlling how a directory is packed into an archive.
+
+# Examples
+
+```rust no_run
+use txtar::{Archive, PackOptions};
+
+let options = PackOptions::new().transform(|_, data| data.replace("/home/alice", "$HOME"));
+let (archive, _) = Archive::from_dir_with("repro/", &options).unwrap();
+```
+**/
+#[derive(Default)]
+pub struct PackOptions<'a> {
+    binary: BinaryPolicy,
+    special: SpecialFilePolicy,
+    read_errors: ReadErrorPolicy,
+    transform: Option<Box<Transform<'a>>>,
+    meta: Option<Vec<String>>,
+    ordering: Ordering,
+    sparse: bool,
+}
+
+impl<'a> PackOptions<'a> {
+    pub fn new() -> PackOptions<'a> {
+        PackOptions::default()
+    }
+
+    /// Sets the policy for binary files.
+    pub fn binary(mut self, policy: BinaryPolicy) -> PackOptions<'a> {
+        self.binary = policy;
+        self
+    }
+
+    /// Sets the policy for special files.
+    pub fn special_files(mut self, policy: SpecialFilePolicy) -> PackOptions<'a> {
+        self.special = policy;
+        self
+    }
+
+    /// Sets the policy for paths that cannot be read. The directory being
+    /// packed must always be readable.
+    pub fn read_errors(mut self, policy: ReadErrorPolicy) -> PackOptions<'a> {
+        self.read_errors = policy;
+        self
+    }
+
+    /// Sets a function that rewrites the data of each file before it is
+    /// added to the archive, e.g. to scrub home directories, host names
+    /// or timestamps. It is called with the path of the file relative to
+    /// the packed directory.
+    pub fn transform<F>(mut self, transform: F) -> PackOptions<'a>
+    where
+        F: Fn(&Path, String) -> String + 'a,
+    {
+        self.transform = Some(Box::new(transform));
+        self
+    }
+
+    /// Sets the order of the packed files. By default files are added in
+    /// the order the directory is walked, which is lexicographic.
+    pub fn ordering(mut self, ordering: Ordering) -> PackOptions<'a> {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Sets whether runs of NUL bytes at least a block long are left out
+    /// of the packed files, as in sparse disk images. Their positions are
+    /// recorded in the `sparse` [metadata](File::metadata) of the file,
+    /// and [`Archive::materialize_with`] puts them back.
+    ///
+    /// The remaining data must still be text without NUL bytes. The
+    /// transform is not applied to files with holes, as it would move
+    /// them, and they are not transcoded.
+    pub fn sparse(mut self, sparse: bool) -> PackOptions<'a> {
+        self.sparse = sparse;
+        self
+    }
+
+    /// Appends a [`Meta::PATH`] member recording the crate version, the
+    /// platform and the given environment variables.
+    pub fn meta<I, S>(mut self, vars: I) -> PackOptions<'a>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.meta = Some(vars.into_iter().map(|v| v.as_ref().to_owned()).collect());
+        self
+    }
+}
+
+impl fmt::Debug for PackOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PackOptions")
+            .field("binary", &self.binary)
+            .field("special", &self.special)
+            .field("read_errors", &self.read_errors)
+            .field("transform", &self.transform.is_some())
+            .field("meta", &self.meta)
+            .field("ordering", &self.ordering)
+            .field("sparse", &self.sparse)
+            .finish()
+    }
+}
+
+/// Files that were left out while packing a directory.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct PackReport {
+    pub skipped: Vec<Skipped>,
+}
+
+/// A file that was left out while packing a directory.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Skipped {
+    /// The path of the file, relative to the packed directory.
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SkipReason {
+    Binary,
+    SpecialFile,
+    /// The path could not be read, for the given reason.
+    Unreadable(io::ErrorKind),
+}
+
+impl Archive {
+    /// Packs the text files in the directory at the given path into an
+    /// archive, skipping binary files.
+    ///
+    /// Files are added in lexicographic order of their paths relative to
+    /// the directory.
+    ///
+    /// # Errors
+    ///
+    /// Any errors caused by the underlying I/O operations will be
+    /// propagated.
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Archive, PackError> {
+        let (archive, _) = Archive::from_dir_with(path, &PackOptions::default())?;
+        Ok(archive)
+    }
+
+    /// Packs the directory at the given path into an archive according to
+    /// the given options, reporting the files that were left out.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if a file violates a policy set to
+    /// error. Additionally, any errors caused by the underlying I/O
+    /// operations will be propagated.
+    pub fn from_dir_with<P: AsRef<Path>>(
+        path: P,
+        options: &PackOptions<'_>,
+    ) -> Result<(Archive, PackReport), PackError> {
+        let mut packer = Packer {
+            root: path.as_ref(),
+            options,
+            files: Vec::new(),
+            report: PackReport::default(),
+        };
+        packer.walk(Path::new(""))?;
+
+        let mut archive = Archive::from_parts("", packer.files);
+        archive.sort(options.ordering);
+        if let Some(vars) = &options.meta {
+            let meta = Meta::capture(vars);
+            archive.files.push(File::new(Meta::PATH, &meta.to_string()));
+        }
+
+        Ok((archive, packer.report))
+    }
+}
+
+impl File {
+    /// Reads the file at the given path into a file named after the path.
+    ///
+    /// # Errors
+    ///
+    /// This function will error with [`PackError::Binary`] if the file is
+    /// binary. Additionally, any errors caused by the underlying I/O
+    /// operations will be propagated.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<File, PackError> {
+        let path = path.as_ref();
+        File::read(path.to_owned(), path)
+    }
+
+    /// Reads the file at `root.join(name)` into a file called `name`,
+    /// as when packing the directory `root`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error in the same cases as
+    /// [`File::from_path`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust no_run
+    /// use txtar::{Archive, File};
+    ///
+    /// let mut archive = Archive::new();
+    /// archive.push(File::from_path_in("project", "src/main.rs").unwrap());
+    /// assert!(archive.contains("src/main.rs"));
+    /// ```
+    pub fn from_path_in<P, N>(root: P, name: N) -> Result<File, PackError>
+    where
+        P: AsRef<Path>,
+        N: AsRef<Path>,
+    {
+        let name = name.as_ref();
+        File::read(name.to_owned(), &root.as_ref().join(name))
+    }
+
+    fn read(name: PathBuf, path: &Path) -> Result<File, PackError> {
+        let data = match String::from_utf8(fs::read(path)?) {
+            Ok(data) if !data.contains('\0') => data,
+            _ => return Err(PackError::Binary(name)),
+        };
+        let mut file = File::from_string(name, data);
+        file.set_origin(path);
+        Ok(file)
+    }
+}
+
+struct Packer<'a> {
+    root: &'a Path,
+    options: &'a PackOptions<'a>,
+    files: Vec<File>,
+    report: PackReport,
+}
+
+impl Packer<'_> {
+    fn walk(&mut self, rel: &Path) -> Result<(), PackError> {
+        let mut entries = fs::read_dir(self.root.join(rel))?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|e| e.file_name());
+
+        for entry in entries {
+            let rel = rel.join(entry.file_name());
+            match self.add_entry(&rel, &entry.path()) {
+                Err(PackError::Io(e)) if self.options.read_errors == ReadErrorPolicy::Skip => {
+                    self.skip(rel, SkipReason::Unreadable(e.kind()));
+                }
+                result => result?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_entry(&mut self, rel: &Path, path: &Path) -> Result<(), PackError> {
+        let metadata = fs::metadata(path)?;
+        if metadata.is_dir() {
+            self.walk(rel)
+        } else if metadata.is_file() {
+            self.add_file(rel.to_owned(), path)
+        } else {
+            match self.options.special {
+                SpecialFilePolicy::Skip => {
+                    self.skip(rel.to_owned(), SkipReason::SpecialFile);
+                    Ok(())
+                }
+                SpecialFilePolicy::Error => Err(PackError::SpecialFile(rel.to_owned())),
+            }
+        }
+    }
+
+    fn add_file(&mut self, rel: PathBuf, path: &Path) -> Result<(), PackError> {
+        let mut bytes = fs::read(path)?;
+        let mut holes = String::new();
+        if self.options.sparse {
+            (bytes, holes) = punch_holes(bytes);
+        }
+        let data = match self.options.binary {
+            _ if !holes.is_empty() => String::from_utf8(bytes).ok(),
+            #[cfg(feature = "encoding")]
+            BinaryPolicy::Transcode => encoding::to_utf8(&bytes).ok(),
+            #[cfg(feature = "encoding")]
+            BinaryPolicy::TranscodeLatin1 => encoding::to_utf8_or_latin1(&bytes).ok(),
+            _ => String::from_utf8(bytes).ok(),
+        };
+        match data {
+            Some(data) if !data.contains('\0') => {
+                let data = match &self.options.transform {
+                    Some(transform) if holes.is_empty() => transform(&rel, data),
+                    _ => data,
+                };
+                let mut file = File::new(rel, &data);
+                file.set_origin(path);
+                if !holes.is_empty() {
+                    file.metadata.insert(SPARSE.to_owned(), holes);
+                }
+                self.files.push(file);
+                Ok(())
+            }
+            _ => match self.options.binary {
+                BinaryPolicy::Error => Err(PackError::Binary(rel)),
+                _ => {
+                    self.skip(rel, SkipReason::Binary);
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    fn skip(&mut self, path: PathBuf, reason: SkipReason) {
+        self.report.skipped.push(Skipped { path, reason });
+    }
+}
+
+/// Removes the runs of NUL bytes that are at least a block long, returning
+/// the remaining bytes and the holes left in `sparse` metadata format.
+fn punch_holes(bytes: Vec<u8>) -> (Vec<u8>, String) {
+    let mut data = Vec::new();
+    let mut holes = Vec::new();
+    let (mut rest, mut offset) = (&bytes[..], 0);
+    while let Some(start) = rest.iter().position(|&b| b == 0) {
+        let end = start + rest[start..].iter().take_while(|&&b| b == 0).count();
+        if end - start >= SPARSE_BLOCK {
+            data.extend_from_slice(&rest[..start]);
+            holes.push(format!("{}+{}", offset + start, end - start));
+        } else {
+            data.extend_from_slice(&rest[..end]);
+        }
+        (rest, offset) = (&rest[end..], offset + end);
+    }
+    if holes.is_empty() {
+        return (bytes, String::new());
+    }
+
+    data.extend_from_slice(rest);
+    (data, holes.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::{prelude::*, TempDir};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn from_dir_binary() {
+        let dir = TempDir::new().unwrap();
+        dir.child("b/nested.txt").write_str("nested").unwrap();
+        dir.child("a.txt").write_str("a\n").unwrap();
+        dir.child("bin").write_binary(b"\0\x01\x02").unwrap();
+        dir.child("latin1").write_binary(b"caf\xe9").unwrap();
+
+        let (archive, report) = Archive::from_dir_with(&dir, &PackOptions::new()).unwrap();
+        assert_eq!(
+            archive.to_string(),
+            "-- a.txt --\na\n-- b/nested.txt --\nnested\n"
+        );
+        let skipped: Vec<_> = report.skipped.iter().map(|s| s.path.clone()).collect();
+        assert_eq!(skipped, [PathBuf::from("bin"), PathBuf::from("latin1")]);
+
+        let options = PackOptions::new().binary(BinaryPolicy::Error);
+        match Archive::from_dir_with(&dir, &options) {
+            Err(PackError::Binary(p)) => assert_eq!(p, PathBuf::from("bin")),
+            other => panic!("expected `PackError::Binary`, got {:?}", other),
+        }
+
+        #[cfg(feature = "encoding")]
+        {
+            dir.child("utf16").write_binary(b"\xff\xfeh\0i\0").unwrap();
+            let options = PackOptions::new().binary(BinaryPolicy::Transcode);
+            let (archive, report) = Archive::from_dir_with(&dir, &options).unwrap();
+            assert_eq!(
+                archive.to_string(),
+                "-- a.txt --\na\n-- b/nested.txt --\nnested\n-- utf16 --\nhi\n"
+            );
+            assert_eq!(report.skipped.len(), 2);
+
+            let options = PackOptions::new().binary(BinaryPolicy::TranscodeLatin1);
+            let (archive, report) = Archive::from_dir_with(&dir, &options).unwrap();
+            assert!(archive.to_string().contains("-- latin1 --\ncafé\n"));
+            assert_eq!(report.skipped.len(), 1);
+        }
+    }
+
+    #[test]
+    fn from_dir_sparse() {
+        let dir = TempDir::new().unwrap();
+        let image = [&b"head"[..], &[0; 3 * SPARSE_BLOCK], b"tail\n"].concat();
+        dir.child("image").write_binary(&image).unwrap();
+        dir.child("short").write_binary(b"a\0b\n").unwrap();
+
+        let options = PackOptions::new()
+            .sparse(true)
+            .transform(|_, data| data.to_uppercase());
+        let (archive, report) = Archive::from_dir_with(&dir, &options).unwrap();
+        assert_eq!(
+            archive.to_string(),
+            format!("-- image # sparse=4+{} --\nheadtail\n", 3 * SPARSE_BLOCK)
+        );
+        assert_eq!(report.skipped[0].path, PathBuf::from("short"));
+
+        for sparse in [false, true] {
+            let out = dir.child(format!("out-{sparse}"));
+            let options = crate::MaterializeOptions::new().sparse(sparse);
+            archive.materialize_with(&out, &options).unwrap();
+            assert_eq!(fs::read(out.child("image")).unwrap(), image);
+        }
+
+        let mut archive = archive;
+        let holes = format!("20+{}", SPARSE_BLOCK);
+        archive.files[0].metadata_mut().insert(SPARSE.into(), holes);
+        let out = dir.child("out-invalid");
+        assert!(archive.materialize_with(&out, &Default::default()).is_err());
+    }
+
+    #[test]
+    fn from_dir_transform() {
+        let dir = TempDir::new().unwrap();
+        dir.child("log").write_str("user=alice\n").unwrap();
+        dir.child("sub/conf")
+            .write_str("home=/home/alice\n")
+            .unwrap();
+
+        let options = PackOptions::new().transform(|path, data| {
+            format!("# {}\n{}", path.display(), data.replace("alice", "<user>"))
+        });
+        let (archive, _) = Archive::from_dir_with(&dir, &options).unwrap();
+        assert_eq!(
+            archive.to_string(),
+            "-- log --\n# log\nuser=<user>\n-- sub/conf --\n# sub/conf\nhome=/home/<user>\n"
+        );
+        assert_eq!(
+            archive.files[1].origin(),
+            Some(dir.child("sub/conf").path())
+        );
+    }
+
+    #[test]
+    fn from_dir_ordering() {
+        let dir = TempDir::new().unwrap();
+        dir.child("a.txt").touch().unwrap();
+        dir.child("b/c.txt").touch().unwrap();
+
+        let options = PackOptions::new().ordering(Ordering::DirectoriesFirst);
+        let (archive, _) = Archive::from_dir_with(&dir, &options).unwrap();
+        assert_eq!(archive.to_string(), "-- b/c.txt --\n-- a.txt --\n");
+    }
+
+    #[test]
+    fn from_dir_meta() {
+        let dir = TempDir::new().unwrap();
+        dir.child("a").write_str("a\n").unwrap();
+
+        let options = PackOptions::new().meta(["PATH"]);
+        let (archive, _) = Archive::from_dir_with(&dir, &options).unwrap();
+        let meta = archive.meta().unwrap();
+        assert_eq!(meta, Meta::capture(["PATH"]));
+        assert_eq!(archive.files.last().unwrap().name, Path::new(Meta::PATH));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_dir_special_files() {
+        use std::os::unix::net::UnixListener;
+
+        let dir = TempDir::new().unwrap();
+        dir.child("a.txt").write_str("a\n").unwrap();
+        let _listener = UnixListener::bind(dir.child("sock").path()).unwrap();
+
+        let (archive, report) = Archive::from_dir_with(&dir, &PackOptions::new()).unwrap();
+        assert_eq!(archive.to_string(), "-- a.txt --\na\n");
+        assert_eq!(
+            report.skipped,
+            [Skipped {
+                path: PathBuf::from("sock"),
+                reason: SkipReason::SpecialFile,
+            }]
+        );
+
+        let options = PackOptions::new().special_files(SpecialFilePolicy::Error);
+        match Archive::from_dir_with(&dir, &options) {
+            Err(PackError::SpecialFile(p)) => assert_eq!(p, PathBuf::from("sock")),
+            other => panic!("expected `PackError::SpecialFile`, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn file_from_path() {
+        let dir = TempDir::new().unwrap();
+        dir.child("src/main.rs").write_str("fn main() {}").unwrap();
+        dir.child("bin").write_binary(b"\0").unwrap();
+
+        let file = File::from_path_in(&dir, "src/main.rs").unwrap();
+        assert_eq!(file.name(), Path::new("src/main.rs"));
+        assert_eq!(file.data(), "fn main() {}\n");
+        assert_eq!(file.origin(), Some(dir.child("src/main.rs").path()));
+
+        let path = dir.child("src/main.rs");
+        let file = File::from_path(&path).unwrap();
+        assert_eq!(file.name(), path.path());
+
+        let err = File::from_path_in(&dir, "bin").unwrap_err();
+        assert!(matches!(err, PackError::Binary(name) if name == Path::new("bin")));
+        let err = File::from_path(dir.child("missing")).unwrap_err();
+        assert!(matches!(err, PackError::Io(e) if e.kind() == std::io::ErrorKind::NotFound));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_dir_read_errors() {
+        let dir = TempDir::new().unwrap();
+        dir.child("a.txt").write_str("a\n").unwrap();
+        dir.child("broken").symlink_to_file("missing").unwrap();
+
+        let err = Archive::from_dir(&dir).unwrap_err();
+        assert!(matches!(err, PackError::Io(e) if e.kind() == io::ErrorKind::NotFound));
+
+        let options = PackOptions::new().read_errors(ReadErrorPolicy::Skip);
+        let (archive, report) = Archive::from_dir_with(&dir, &options).unwrap();
+        assert_eq!(archive.to_string(), "-- a.txt --\na\n");
+        assert_eq!(
+            report.skipped,
+            [Skipped {
+                path: "broken".into(),
+                reason: SkipReason::Unreadable(io::ErrorKind::NotFound),
+            }]
+        );
+    }
+}
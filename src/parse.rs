@@ -0,0 +1,380 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{profile, Archive, File, NameRules, ParseError, Profile};
+
+pub(crate) const INCLUDE: &str = " <= ";
+pub(crate) const ANNOTATIONS: &str = " # ";
+
+/// Loads the data of files included with a `-- name <= source --` marker.
+pub trait Resolver {
+    fn resolve(&self, source: &str) -> io::Result<String>;
+}
+
+impl<F> Resolver for F
+where
+    F: Fn(&str) -> io::Result<String>,
+{
+    fn resolve(&self, source: &str) -> io::Result<String> {
+        self(source)
+    }
+}
+
+/// A resolver that reads included files relative to a directory.
+///
+/// Sources must be relative paths without `..` components, so archive
+/// text cannot read files outside the directory through them. Others
+/// fail with [`io::ErrorKind::PermissionDenied`]. Symbolic links inside
+/// the directory are followed.
+#[derive(Debug, Clone)]
+pub struct DirResolver {
+    root: PathBuf,
+}
+
+impl DirResolver {
+    pub fn new<P: AsRef<Path>>(root: P) -> DirResolver {
+        DirResolver {
+            root: root.as_ref().to_owned(),
+        }
+    }
+}
+
+impl Resolver for DirResolver {
+    fn resolve(&self, source: &str) -> io::Result<String> {
+        let contained = Path::new(source)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !contained {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "include outside of the resolver directory",
+            ));
+        }
+        fs::read_to_string(self.root.join(source))
+    }
+}
+
+type Rename<'a> = dyn Fn(&Path) -> Option<PathBuf> + 'a;
+
+/**
+Options controlling how txtar text is parsed.
+
+# Examples
+
+```rust no_run
+use txtar::{Archive, DirResolver, ParseOptions};
+
+let txt = "\
+-- small.json --
+{}
+-- big.json <= big.json --
+";
+
+let options = ParseOptions::new().resolver(DirResolver::new("fixtures"));
+let archive = Archive::parse_with(txt, &options).unwrap();
+```
+**/
+#[derive(Default)]
+pub struct ParseOptions<'a> {
+    resolver: Option<Box<dyn Resolver + 'a>>,
+    annotations: bool,
+    profile: Profile,
+    rename: Option<Box<Rename<'a>>>,
+    name_rules: Option<NameRules>,
+}
+
+impl<'a> ParseOptions<'a> {
+    pub fn new() -> ParseOptions<'a> {
+        ParseOptions::default()
+    }
+
+    /// Enables include directives, resolving them with the given resolver.
+    ///
+    /// A marker of the form `-- name <= source --` then adds a file
+    /// called `name` whose data is loaded from `source`. The marker must
+    /// not be followed by any data of its own.
+    pub fn resolver<R: Resolver + 'a>(mut self, resolver: R) -> ParseOptions<'a> {
+        self.resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Sets whether marker annotations are parsed into [`File::metadata`].
+    ///
+    /// A marker of the form `-- name # key=value other=value --` then
+    /// adds a file called `name` with the given metadata. Annotations
+    /// come before an include directive's ` <= source`, if any.
+    ///
+    /// [`File::metadata`]: crate::File::metadata
+    pub fn annotations(mut self, annotations: bool) -> ParseOptions<'a> {
+        self.annotations = annotations;
+        self
+    }
+
+    /// Sets how file markers are recognized. See [`Profile`] for the
+    /// differences.
+    pub fn profile(mut self, profile: Profile) -> ParseOptions<'a> {
+        self.profile = profile;
+        self
+    }
+
+    /// Sets a function that maps the name of each file as it is parsed.
+    /// Files for which it returns `None` are left out, and their include
+    /// directives are not resolved.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use txtar::{Archive, ParseOptions};
+    ///
+    /// let options = ParseOptions::new()
+    ///     .rename(|name: &Path| Some(Path::new("src").join(name.strip_prefix("old/").ok()?)));
+    /// let archive = Archive::parse_with("-- old/a.rs --\n-- b.rs --\n", &options).unwrap();
+    /// assert_eq!(archive.to_string(), "-- src/a.rs --\n");
+    /// ```
+    pub fn rename<F>(mut self, rename: F) -> ParseOptions<'a>
+    where
+        F: Fn(&Path) -> Option<PathBuf> + 'a,
+    {
+        self.rename = Some(Box::new(rename));
+        self
+    }
+
+    /// Sets rules that the names of parsed files must follow, after
+    /// renaming.
+    pub fn name_rules(mut self, rules: NameRules) -> ParseOptions<'a> {
+        self.name_rules = Some(rules);
+        self
+    }
+}
+
+impl fmt::Debug for ParseOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("resolver", &self.resolver.is_some())
+            .field("annotations", &self.annotations)
+            .field("profile", &self.profile)
+            .field("rename", &self.rename.is_some())
+            .field("name_rules", &self.name_rules)
+            .finish()
+    }
+}
+
+impl Archive {
+    /// Read an archive from a string of txtar data according to the
+    /// given options.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if an include directive cannot be
+    /// resolved or is followed by data, or if the text does not conform
+    /// to [`Profile::Strict`] when that profile is selected, or if a file
+    /// name breaks the [name rules](ParseOptions::name_rules).
+    pub fn parse_with(s: &str, options: &ParseOptions) -> Result<Archive, ParseError> {
+        if options.profile == Profile::Strict {
+            profile::check_strict(s)?;
+        }
+
+        let (comment, mut next) = crate::split_file_markers(s, options.profile);
+        let mut files = Vec::with_capacity(Archive::capacity_hint(s));
+        while let Some((marker, rest)) = next {
+            let (data, after) = crate::split_file_markers(rest, options.profile);
+            next = after;
+            if let Some(file) = options.file(marker, data)? {
+                files.push(file);
+            }
+        }
+
+        Ok(Archive::from_parts(comment, files))
+    }
+}
+
+impl ParseOptions<'_> {
+    /// Builds the file for a marker, or returns `None` if the rename
+    /// function drops it.
+    fn file(&self, marker: &str, data: &str) -> Result<Option<File>, ParseError> {
+        let mut name = marker;
+        let include = match &self.resolver {
+            Some(_) => name.split_once(INCLUDE).map(|(n, include)| {
+                name = n.trim();
+                include.trim()
+            }),
+            None => None,
+        };
+
+        let mut metadata = BTreeMap::new();
+        if self.annotations {
+            if let Some((n, annotations)) = name.split_once(ANNOTATIONS) {
+                for annotation in annotations.split_whitespace() {
+                    let (key, value) = annotation.split_once('=').unwrap_or((annotation, ""));
+                    metadata.insert(key.to_owned(), value.to_owned());
+                }
+                name = n.trim();
+            }
+        }
+
+        let name = match &self.rename {
+            Some(rename) => match rename(Path::new(name)) {
+                Some(name) => name,
+                None => return Ok(None),
+            },
+            None => PathBuf::from(name),
+        };
+        if let Some(rules) = &self.name_rules {
+            rules
+                .check(&name)
+                .map_err(|reason| ParseError::InvalidName(name.display().to_string(), reason))?;
+        }
+
+        let mut file = match (include, &self.resolver) {
+            (Some(include), Some(resolver)) => {
+                if !data.is_empty() {
+                    return Err(ParseError::IncludeWithData(name.display().to_string()));
+                }
+                let data = resolver
+                    .resolve(include)
+                    .map_err(|error| ParseError::Resolve {
+                        name: name.display().to_string(),
+                        include: include.to_owned(),
+                        error,
+                    })?;
+                File::new(name, &data)
+            }
+            _ => File::new(name, data),
+        };
+        file.metadata = metadata;
+
+        Ok(Some(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+    use std::collections::HashMap;
+
+    #[test]
+    fn parse_includes() {
+        let sources = HashMap::from([("gen/big.json", "[1, 2, 3]")]);
+        let resolver = |source: &str| {
+            sources
+                .get(source)
+                .map(|s| s.to_string())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        };
+        let options = ParseOptions::new().resolver(resolver);
+
+        let txt = "-- a --\na\n-- big.json <= gen/big.json --\n-- b --\nb\n";
+        let archive = Archive::parse_with(txt, &options).unwrap();
+        assert_eq!(
+            archive.to_string(),
+            "-- a --\na\n-- big.json --\n[1, 2, 3]\n-- b --\nb\n"
+        );
+
+        let plain = Archive::parse_with(txt, &ParseOptions::new()).unwrap();
+        assert_eq!(plain, Archive::from(txt));
+
+        match Archive::parse_with("-- x <= missing --\n", &options) {
+            Err(ParseError::Resolve { name, include, .. }) => {
+                assert_eq!((name.as_str(), include.as_str()), ("x", "missing"))
+            }
+            other => panic!("expected `ParseError::Resolve`, got {:?}", other),
+        }
+        match Archive::parse_with("-- x <= gen/big.json --\ninline\n", &options) {
+            Err(ParseError::IncludeWithData(name)) => assert_eq!(name, "x"),
+            other => panic!("expected `ParseError::IncludeWithData`, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dir_resolver() {
+        use assert_fs::{prelude::*, TempDir};
+
+        let dir = TempDir::new().unwrap();
+        dir.child("root/a.txt").write_str("a\n").unwrap();
+        dir.child("secret").write_str("secret\n").unwrap();
+        let options = ParseOptions::new().resolver(DirResolver::new(dir.child("root")));
+
+        let archive = Archive::parse_with("-- a <= ./a.txt --\n", &options).unwrap();
+        assert_eq!(archive.to_string(), "-- a --\na\n");
+
+        let absolute = dir.child("secret").path().display().to_string();
+        for source in ["../secret", "a/../../secret", absolute.as_str()] {
+            let txt = format!("-- x <= {source} --\n");
+            match Archive::parse_with(&txt, &options) {
+                Err(ParseError::Resolve { error, .. }) => {
+                    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied, "{source}")
+                }
+                other => panic!("expected `ParseError::Resolve`, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_rename() {
+        let resolver = |source: &str| -> io::Result<String> { panic!("resolved {source}") };
+        let options = ParseOptions::new().resolver(resolver).rename(|name| {
+            match name.strip_prefix("vendor") {
+                Ok(_) => None,
+                Err(_) => Some(Path::new("root").join(name)),
+            }
+        });
+
+        let txt = "c\n-- a --\na\n-- vendor/x <= x --\n-- b/c --\nbc\n";
+        let archive = Archive::parse_with(txt, &options).unwrap();
+        assert_eq!(
+            archive.to_string(),
+            "c\n-- root/a --\na\n-- root/b/c --\nbc\n"
+        );
+    }
+
+    #[test]
+    fn parse_annotations() {
+        let txt = "-- a # mode=0755 x-tool=yes --\na\n-- b --\n-- c # flag --\n";
+        let options = ParseOptions::new().annotations(true);
+        let mut archive = Archive::parse_with(txt, &options).unwrap();
+
+        let names: Vec<_> = archive.files.iter().map(|f| f.name()).collect();
+        assert_eq!(names, ["a", "b", "c"].map(Path::new));
+        let metadata = [("mode", "0755"), ("x-tool", "yes")].map(|(k, v)| (k.into(), v.into()));
+        assert_eq!(archive.files[0].metadata(), &BTreeMap::from(metadata));
+        assert!(archive.files[1].metadata().is_empty());
+        assert_eq!(archive.files[2].metadata()["flag"], "");
+
+        archive.files[1]
+            .metadata_mut()
+            .insert("owner".into(), "ci".into());
+        let txt = archive.to_string();
+        assert_eq!(
+            txt,
+            "-- a # mode=0755 x-tool=yes --\na\n-- b # owner=ci --\n-- c # flag= --\n"
+        );
+        assert_eq!(Archive::parse_with(&txt, &options).unwrap(), archive);
+
+        let plain = Archive::parse_with(&txt, &ParseOptions::new()).unwrap();
+        assert_eq!(plain.files[0].name(), Path::new("a # mode=0755 x-tool=yes"));
+    }
+
+    #[test]
+    fn parse_annotated_include() {
+        let resolver = |source: &str| match source {
+            "gen/run.sh" => Ok("#!/bin/sh\n".to_owned()),
+            _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+        };
+        let options = ParseOptions::new().annotations(true).resolver(resolver);
+
+        let txt = "-- run.sh # mode=0755 <= gen/run.sh --\n";
+        let archive = Archive::parse_with(txt, &options).unwrap();
+        let file = &archive.files[0];
+        assert_eq!(file.name(), Path::new("run.sh"));
+        assert_eq!(file.data(), "#!/bin/sh\n");
+        assert_eq!(
+            file.metadata(),
+            &BTreeMap::from([("mode".into(), "0755".into())])
+        );
+    }
+}
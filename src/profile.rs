@@ -0,0 +1,152 @@
+use crate::{ParseError, MARKER, MARKER_END};
+
+/**
+A set of choices where txtar implementations diverge.
+
+| Input                               | `Lenient`    | `GoCompat`        | `Strict` |
+|-------------------------------------|--------------|-------------------|----------|
+| `-- name --\r` marker line          | file `name`  | not a marker      | error    |
+| `--  --` marker without a name      | not a marker | not a marker      | error    |
+| `--  name  --` padded name          | file `name`  | file `name`       | error    |
+| `-- name --` at the end, no newline | file `name`  | file `name`       | error    |
+
+When formatting, [`Profile::GoCompat`] leaves out the metadata
+annotations of files, so the output is exactly what Go's `txtar.Format`
+produces. The other profiles write them.
+
+# Examples
+
+```rust
+use txtar::{Archive, ParseOptions, Profile};
+
+let txt = "-- a --\r\nhello\r\n";
+
+let archive = Archive::parse_with(txt, &ParseOptions::new()).unwrap();
+assert_eq!(archive.to_string(), "-- a --\nhello\r\n");
+
+let options = ParseOptions::new().profile(Profile::GoCompat);
+let archive = Archive::parse_with(txt, &options).unwrap();
+assert_eq!(archive.to_string(), txt);
+
+let options = ParseOptions::new().profile(Profile::Strict);
+assert!(Archive::parse_with(txt, &options).is_err());
+```
+**/
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Profile {
+    /// Accept common variations, such as CRLF line endings.
+    #[default]
+    Lenient,
+    /// Match the behavior of Go's `golang.org/x/tools/txtar` exactly.
+    GoCompat,
+    /// Reject input that implementations interpret differently.
+    Strict,
+}
+
+/// Checks that `s` parses the same with every profile.
+pub(crate) fn check_strict(s: &str) -> Result<(), ParseError> {
+    let mut lines = s.split('\n').enumerate().peekable();
+    while let Some((i, line)) = lines.next() {
+        let Some(name) = line
+            .trim_end_matches('\r')
+            .strip_prefix(MARKER)
+            .and_then(|l| l.strip_suffix(MARKER_END))
+        else {
+            continue;
+        };
+
+        let reason = if line.ends_with('\r') {
+            "carriage return after file marker"
+        } else if name.trim().is_empty() {
+            "empty file name"
+        } else if name.trim() != name {
+            "whitespace around file name"
+        } else if lines.peek().is_none() {
+            "file marker at end of input without newline"
+        } else {
+            continue;
+        };
+
+        return Err(ParseError::Strict {
+            line: i + 1,
+            reason,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Archive, FormatOptions, ParseOptions};
+    use similar_asserts::assert_eq;
+
+    fn parse(s: &str, profile: Profile) -> Result<String, String> {
+        let options = ParseOptions::new().profile(profile);
+        Archive::parse_with(s, &options)
+            .map(|a| {
+                a.display_with(&FormatOptions::new().profile(profile))
+                    .to_string()
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn profiles() {
+        let cases = [
+            (
+                "-- a --\r\nx\n",
+                Ok("-- a --\nx\n"),
+                Ok("-- a --\r\nx\n"),
+                Err("line 1: carriage return after file marker"),
+            ),
+            (
+                "c\n--  --\nx\n",
+                Ok("c\n--  --\nx\n"),
+                Ok("c\n--  --\nx\n"),
+                Err("line 2: empty file name"),
+            ),
+            (
+                "--  a  --\nx\n",
+                Ok("-- a --\nx\n"),
+                Ok("-- a --\nx\n"),
+                Err("line 1: whitespace around file name"),
+            ),
+            (
+                "-- a --\nx\n-- b --",
+                Ok("-- a --\nx\n-- b --\n"),
+                Ok("-- a --\nx\n-- b --\n"),
+                Err("line 3: file marker at end of input without newline"),
+            ),
+            (
+                "-- a # k=v --\n",
+                Ok("-- a # k=v --\n"),
+                Ok("-- a # k=v --\n"),
+                Ok("-- a # k=v --\n"),
+            ),
+        ];
+
+        for (txt, lenient, go, strict) in cases {
+            let expected = [lenient, go, strict].map(|r| r.map(String::from).map_err(String::from));
+            let actual =
+                [Profile::Lenient, Profile::GoCompat, Profile::Strict].map(|p| parse(txt, p));
+            assert_eq!(actual, expected, "parse[{txt:?}]");
+        }
+
+        for profile in [Profile::Lenient, Profile::GoCompat] {
+            let options = ParseOptions::new().profile(profile);
+            let archive = Archive::parse_with("c\n--  --\nx\n", &options).unwrap();
+            assert_eq!(archive.files().len(), 0, "{profile:?}");
+            assert_eq!(archive.comment(), "c\n--  --\nx\n");
+        }
+
+        let mut archive = Archive::from("-- a --\n");
+        archive.files[0]
+            .metadata_mut()
+            .insert("k".into(), "v".into());
+        let go = FormatOptions::new().profile(Profile::GoCompat);
+        assert_eq!(archive.display_with(&go).to_string(), "-- a --\n");
+        assert_eq!(archive.to_string(), "-- a # k=v --\n");
+    }
+}
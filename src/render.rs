@@ -0,0 +1,202 @@
+use std::{fmt::Write as _, path::Path};
+
+use crate::{
+    parse::{ANNOTATIONS, INCLUDE},
+    MaterializeError, ParseError, MARKER, MARKER_END,
+};
+
+impl ParseError {
+    /// Renders the error for display on a terminal: the message, the
+    /// location in the txtar file at `path` whose text is `source`, and
+    /// the offending line with the affected part underlined.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use txtar::{Archive, ParseOptions, Profile};
+    ///
+    /// let txt = "-- a --\nx\n--  b --\n";
+    /// let options = ParseOptions::new().profile(Profile::Strict);
+    /// let err = Archive::parse_with(txt, &options).unwrap_err();
+    /// assert_eq!(
+    ///     err.render(Path::new("test.txtar"), txt),
+    ///     "\
+    /// error: whitespace around file name
+    ///  --> test.txtar:3:5
+    ///   |
+    /// 3 | --  b --
+    ///   |     ^
+    /// ",
+    /// );
+    /// ```
+    pub fn render(&self, path: &Path, source: &str) -> String {
+        let (message, line) = match self {
+            ParseError::Resolve {
+                name,
+                include,
+                error,
+            } => (
+                format!("cannot include {include}: {error}"),
+                marker_line(source, name),
+            ),
+            ParseError::IncludeWithData(name) => (
+                "include directive followed by data".to_owned(),
+                marker_line(source, name),
+            ),
+            ParseError::Strict { line, reason } => ((*reason).to_owned(), Some(*line)),
+            ParseError::InvalidName(name, reason) => {
+                ((*reason).to_owned(), marker_line(source, name))
+            }
+        };
+
+        let mut out = format!("error: {message}\n");
+        let Some((number, text)) =
+            line.and_then(|n| Some((n, source.split('\n').nth(n.checked_sub(1)?)?)))
+        else {
+            writeln!(out, " --> {}", path.display()).unwrap();
+            return out;
+        };
+
+        let text = text.trim_end_matches('\r');
+        let (start, len) = highlight(self, text);
+        let gutter = " ".repeat(number.to_string().len());
+        writeln!(out, "{gutter}--> {}:{number}:{}", path.display(), start + 1).unwrap();
+        writeln!(out, "{gutter} |").unwrap();
+        writeln!(out, "{number} | {text}").unwrap();
+        let pad = " ".repeat(text[..start].chars().count());
+        writeln!(out, "{gutter} | {pad}{}", "^".repeat(len.max(1))).unwrap();
+        out
+    }
+}
+
+impl MaterializeError {
+    /// Renders the error for display on a terminal: the message and the
+    /// directory at `path` the archive was being written to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use txtar::Archive;
+    ///
+    /// let archive = Archive::from("-- ../x --\n");
+    /// let err = archive.materialize("/tmp/out").unwrap_err();
+    /// assert_eq!(
+    ///     err.render(Path::new("/tmp/out")),
+    ///     "\
+    /// error: file would be written outside the target directory
+    ///  --> ../x
+    ///   = note: writing to /tmp/out
+    /// ",
+    /// );
+    /// ```
+    pub fn render(&self, path: &Path) -> String {
+        let (message, name) = match self {
+            MaterializeError::Io(e) => (e.to_string(), None),
+            MaterializeError::DirEscape(name) => (
+                "file would be written outside the target directory".to_owned(),
+                Some(name),
+            ),
+            MaterializeError::Unmapped(name) => {
+                ("no target directory for file".to_owned(), Some(name))
+            }
+            MaterializeError::Rejected(name, reason) => ((*reason).to_owned(), Some(name)),
+            MaterializeError::CaseCollision(name, other) => (
+                format!("file conflicts with {other} on case-insensitive filesystems"),
+                Some(name),
+            ),
+            MaterializeError::NotWritable(_, e) => {
+                (format!("target directory is not writable: {e}"), None)
+            }
+            MaterializeError::OutsideWorkspace(_, root) => (
+                format!("target directory is outside the Cargo workspace at {root}"),
+                None,
+            ),
+        };
+
+        let mut out = format!("error: {message}\n");
+        if let Some(name) = name {
+            writeln!(out, " --> {name}").unwrap();
+        }
+        writeln!(out, "  = note: writing to {}", path.display()).unwrap();
+        out
+    }
+}
+
+/// Returns the line number of the marker of the file with the given name,
+/// ignoring any include directive and annotations on it.
+fn marker_line(source: &str, name: &str) -> Option<usize> {
+    let position = source.split('\n').position(|line| {
+        let Some(rest) = line.trim_end_matches('\r').strip_prefix(MARKER) else {
+            return false;
+        };
+        let rest = rest.strip_suffix(MARKER_END).unwrap_or(rest);
+        let rest = rest.split_once(INCLUDE).map_or(rest, |(n, _)| n);
+        let rest = rest.split_once(ANNOTATIONS).map_or(rest, |(n, _)| n);
+        rest.trim() == name
+    })?;
+    Some(position + 1)
+}
+
+/// Returns the byte offset and width in characters of the part of the
+/// line to underline.
+fn highlight(error: &ParseError, line: &str) -> (usize, usize) {
+    let whole = (0, line.chars().count());
+    let ParseError::Strict { reason, .. } = error else {
+        return whole;
+    };
+    let Some(name) = line.strip_prefix(MARKER) else {
+        return whole;
+    };
+    let name = name.strip_suffix(" --").unwrap_or(name);
+    let trimmed = name.trim();
+    if *reason == "whitespace around file name" && !trimmed.is_empty() {
+        let offset = MARKER.len() + (name.len() - name.trim_start().len());
+        return (offset, trimmed.chars().count());
+    }
+    whole
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Archive, ParseOptions};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn render() {
+        let txt = "comment\n-- a --\nx\n-- b <= missing --\n";
+        let options = ParseOptions::new()
+            .resolver(|_: &str| Err(std::io::Error::new(std::io::ErrorKind::NotFound, "gone")));
+        let err = Archive::parse_with(txt, &options).unwrap_err();
+        assert_eq!(
+            err.render(Path::new("t.txtar"), txt),
+            "\
+error: cannot include missing: gone
+ --> t.txtar:4:1
+  |
+4 | -- b <= missing --
+  | ^^^^^^^^^^^^^^^^^^
+"
+        );
+        assert_eq!(
+            err.render(Path::new("t.txtar"), ""),
+            "error: cannot include missing: gone\n --> t.txtar\n"
+        );
+
+        let txt = "-- ab --\n-- a.txt --\n-- a # k=v <= missing --\n";
+        let err = Archive::parse_with(txt, &options.annotations(true)).unwrap_err();
+        let rendered = err.render(Path::new("t.txtar"), txt);
+        assert!(
+            rendered.contains("3 | -- a # k=v <= missing --\n"),
+            "{rendered}"
+        );
+
+        let err = MaterializeError::Unmapped("README".into());
+        assert_eq!(
+            err.render(Path::new("out")),
+            "error: no target directory for file\n --> README\n  = note: writing to out\n"
+        );
+    }
+}
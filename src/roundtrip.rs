@@ -0,0 +1,90 @@
+use crate::{spans::Span, Archive, RoundtripIssue};
+
+/// Parses `input`, serializes it again and reports the first byte and
+/// line where the result differs from the input.
+///
+/// Text that passes this check is in canonical form: parsing and
+/// formatting it preserves every byte.
+///
+/// # Examples
+///
+/// ```rust
+/// assert!(txtar::check_roundtrip("-- a --\nhello\n").is_ok());
+///
+/// let issue = txtar::check_roundtrip("-- a --\nhello").unwrap_err();
+/// assert_eq!(issue.line, 2);
+/// assert_eq!(issue.offset, 13);
+/// assert_eq!(issue.actual, "hello\n");
+/// ```
+pub fn check_roundtrip(input: &str) -> Result<(), RoundtripIssue> {
+    let output = Archive::from(input).to_string();
+    if input == output {
+        return Ok(());
+    }
+
+    let offset = input
+        .bytes()
+        .zip(output.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut at = offset;
+    while !input.is_char_boundary(at) {
+        at -= 1;
+    }
+
+    let start = input[..at].rfind('\n').map_or(0, |i| i + 1);
+    let input_line = line(input, start);
+    let output_line = line(&output, start);
+
+    Err(RoundtripIssue {
+        line: input[..start].matches('\n').count() + 1,
+        offset,
+        input: input_line,
+        output: output_line,
+        expected: input_line.slice(input).to_owned(),
+        actual: output_line.slice(&output).to_owned(),
+    })
+}
+
+/// Returns the span of the line starting at `start`, including its
+/// newline.
+fn line(s: &str, start: usize) -> Span {
+    let end = s[start..].find('\n').map_or(s.len(), |i| start + i + 1);
+    Span { start, end }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn roundtrip() {
+        assert_eq!(check_roundtrip(""), Ok(()));
+        assert_eq!(check_roundtrip("c\n-- a --\n-- b --\nx\n"), Ok(()));
+
+        let issue = check_roundtrip("c\n-- a --\r\nx\n").unwrap_err();
+        assert_eq!(
+            issue,
+            RoundtripIssue {
+                line: 2,
+                offset: 9,
+                input: Span { start: 2, end: 11 },
+                output: Span { start: 2, end: 10 },
+                expected: "-- a --\r\n".into(),
+                actual: "-- a --\n".into(),
+            }
+        );
+        assert_eq!(
+            issue.to_string(),
+            r#"line 2: "-- a --\r\n" is reserialized as "-- a --\n""#
+        );
+
+        let issue = check_roundtrip("-- é --\nü").unwrap_err();
+        assert_eq!((issue.line, issue.expected.as_str()), (2, "ü"));
+        assert_eq!(issue.offset, 11);
+
+        let issue = check_roundtrip("-- é --\n-- ü --\r\n").unwrap_err();
+        assert_eq!((issue.line, issue.offset), (2, 17));
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "globset")]
+use globset::GlobMatcher;
+
+#[cfg(feature = "globset")]
+use crate::glob;
+use crate::Archive;
+
+/**
+A description of the files an archive must and must not contain.
+
+# Examples
+
+```rust
+use txtar::{Archive, Schema, Violation};
+
+let schema = Schema::new().require("Cargo.toml").forbid("Cargo.lock");
+let archive = Archive::from("-- Cargo.lock --\n");
+
+assert_eq!(
+    archive.check(&schema),
+    [
+        Violation::Missing("Cargo.toml".into()),
+        Violation::Forbidden {
+            rule: "Cargo.lock".into(),
+            name: "Cargo.lock".into(),
+        },
+    ]
+);
+```
+**/
+#[derive(Debug, Default, Clone)]
+pub struct Schema {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Require(PathBuf),
+    Forbid(PathBuf),
+    #[cfg(feature = "globset")]
+    RequireGlob(GlobMatcher),
+    #[cfg(feature = "globset")]
+    ForbidGlob(GlobMatcher),
+}
+
+/// A way in which an archive does not conform to a [`Schema`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Violation {
+    /// A required file is not in the archive.
+    Missing(PathBuf),
+    /// No file in the archive matches a required glob.
+    NoMatch(String),
+    /// A file matches a forbidden name or glob.
+    Forbidden { rule: String, name: PathBuf },
+}
+
+impl Schema {
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    /// Requires a file with the given name.
+    pub fn require<P: AsRef<Path>>(mut self, name: P) -> Schema {
+        self.rules.push(Rule::Require(name.as_ref().to_owned()));
+        self
+    }
+
+    /// Forbids a file with the given name.
+    pub fn forbid<P: AsRef<Path>>(mut self, name: P) -> Schema {
+        self.rules.push(Rule::Forbid(name.as_ref().to_owned()));
+        self
+    }
+
+    /// Requires at least one file matching the given glob.
+    #[cfg(feature = "globset")]
+    pub fn require_glob(mut self, glob: &str) -> Result<Schema, globset::Error> {
+        self.rules.push(Rule::RequireGlob(glob::matcher(glob)?));
+        Ok(self)
+    }
+
+    /// Forbids files matching the given glob.
+    #[cfg(feature = "globset")]
+    pub fn forbid_glob(mut self, glob: &str) -> Result<Schema, globset::Error> {
+        self.rules.push(Rule::ForbidGlob(glob::matcher(glob)?));
+        Ok(self)
+    }
+}
+
+impl Archive {
+    /// Checks the archive against the schema, returning every violation
+    /// in the order of the schema's rules.
+    pub fn check(&self, schema: &Schema) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let names = || self.files.iter().map(|f| &f.name);
+
+        for rule in &schema.rules {
+            match rule {
+                Rule::Require(name) => {
+                    if !names().any(|n| n == name) {
+                        violations.push(Violation::Missing(name.clone()));
+                    }
+                }
+                Rule::Forbid(name) => {
+                    violations.extend(names().filter(|n| *n == name).map(|n| {
+                        Violation::Forbidden {
+                            rule: name.to_string_lossy().into_owned(),
+                            name: n.clone(),
+                        }
+                    }));
+                }
+                #[cfg(feature = "globset")]
+                Rule::RequireGlob(glob) => {
+                    if !names().any(|n| glob.is_match(n)) {
+                        violations.push(Violation::NoMatch(glob.glob().to_string()));
+                    }
+                }
+                #[cfg(feature = "globset")]
+                Rule::ForbidGlob(glob) => {
+                    violations.extend(names().filter(|n| glob.is_match(n)).map(|n| {
+                        Violation::Forbidden {
+                            rule: glob.glob().to_string(),
+                            name: n.clone(),
+                        }
+                    }));
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(all(test, feature = "globset"))]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn check_globs() {
+        let schema = Schema::new()
+            .require("Cargo.toml")
+            .require_glob("src/**/*.rs")
+            .unwrap()
+            .require_glob("tests/*.rs")
+            .unwrap()
+            .forbid_glob("target/**")
+            .unwrap();
+
+        let archive = Archive::from(
+            "-- Cargo.toml --\n-- src/bin/main.rs --\n-- tests/a/b.rs --\n-- target/debug/x --\n-- target/y --\n",
+        );
+        assert_eq!(
+            archive.check(&schema),
+            [
+                Violation::NoMatch("tests/*.rs".into()),
+                Violation::Forbidden {
+                    rule: "target/**".into(),
+                    name: "target/debug/x".into(),
+                },
+                Violation::Forbidden {
+                    rule: "target/**".into(),
+                    name: "target/y".into(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,254 @@
+/*!
+Allocation-free parsing usable in `const` contexts.
+
+The functions in this module work on byte offsets into the txtar text,
+which makes it possible to validate and index embedded fixtures at
+compile time.
+
+# Examples
+
+```rust
+use txtar::spans;
+
+const FIXTURE: &str = "\
+comment
+-- a.txt --
+hello
+-- b.txt --
+world
+";
+
+const _: () = assert!(spans::count(FIXTURE) == 2);
+const B: spans::Entry = match spans::find(FIXTURE, "b.txt") {
+    Some(entry) => entry,
+    None => panic!("b.txt is missing"),
+};
+
+assert_eq!(B.data.slice(FIXTURE), "world\n");
+```
+
+Names are trimmed of ASCII whitespace only, and a marker line whose
+name is empty does not start a new file.
+!*/
+
+/// A range of byte offsets into txtar text.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Returns the text covered by this span.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the span is out of bounds for `s` or does not fall on
+    /// character boundaries, as is the case when `s` is not the text
+    /// the span was computed from.
+    pub fn slice(self, s: &str) -> &str {
+        &s[self.start..self.end]
+    }
+
+    /// Returns the length of the span in bytes.
+    pub const fn len(self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns `true` if the span covers no bytes.
+    pub const fn is_empty(self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// The location of a single file entry within txtar text.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Entry {
+    /// The file name, with surrounding whitespace excluded.
+    pub name: Span,
+    /// The file data, starting after the marker line.
+    pub data: Span,
+}
+
+/// Returns the location of the archive comment.
+pub const fn comment(s: &str) -> Span {
+    let b = s.as_bytes();
+    let end = match next_marker(b, 0) {
+        Some((line_start, _, _)) => line_start,
+        None => b.len(),
+    };
+
+    Span { start: 0, end }
+}
+
+/// Returns the number of files in the archive.
+pub const fn count(s: &str) -> usize {
+    let b = s.as_bytes();
+    let mut n = 0;
+    let mut pos = 0;
+    while let Some((_, _, data_start)) = next_marker(b, pos) {
+        n += 1;
+        pos = data_start;
+    }
+
+    n
+}
+
+/// Returns the location of the file at the given index.
+pub const fn nth(s: &str, index: usize) -> Option<Entry> {
+    let b = s.as_bytes();
+    let mut i = 0;
+    let mut pos = 0;
+    while let Some((_, name, data_start)) = next_marker(b, pos) {
+        if i == index {
+            return Some(entry(b, name, data_start));
+        }
+        i += 1;
+        pos = data_start;
+    }
+
+    None
+}
+
+/// Returns the location of the first file with the given name.
+pub const fn find(s: &str, name: &str) -> Option<Entry> {
+    let b = s.as_bytes();
+    let mut pos = 0;
+    while let Some((_, span, data_start)) = next_marker(b, pos) {
+        if bytes_eq(b, span, name.as_bytes()) {
+            return Some(entry(b, span, data_start));
+        }
+        pos = data_start;
+    }
+
+    None
+}
+
+const fn entry(b: &[u8], name: Span, data_start: usize) -> Entry {
+    let data_end = match next_marker(b, data_start) {
+        Some((line_start, _, _)) => line_start,
+        None => b.len(),
+    };
+
+    Entry {
+        name,
+        data: Span {
+            start: data_start,
+            end: data_end,
+        },
+    }
+}
+
+/// Finds the first marker line starting at or after `pos`, which must be
+/// the start of a line. Returns the start of the marker line, the name
+/// and the start of the following line.
+const fn next_marker(b: &[u8], mut pos: usize) -> Option<(usize, Span, usize)> {
+    while pos < b.len() {
+        let end = line_end(b, pos);
+        let next = if end < b.len() { end + 1 } else { end };
+        if let Some(name) = marker_name(b, pos, end) {
+            return Some((pos, name, next));
+        }
+        pos = next;
+    }
+
+    None
+}
+
+const fn line_end(b: &[u8], mut pos: usize) -> usize {
+    while pos < b.len() && b[pos] != b'\n' {
+        pos += 1;
+    }
+
+    pos
+}
+
+const fn marker_name(b: &[u8], start: usize, mut end: usize) -> Option<Span> {
+    while end > start && b[end - 1] == b'\r' {
+        end -= 1;
+    }
+    if end - start < 6 {
+        return None;
+    }
+    if !(b[start] == b'-' && b[start + 1] == b'-' && b[start + 2] == b' ') {
+        return None;
+    }
+    if !(b[end - 3] == b' ' && b[end - 2] == b'-' && b[end - 1] == b'-') {
+        return None;
+    }
+
+    let mut name_start = start + 3;
+    let mut name_end = end - 3;
+    while name_start < name_end && b[name_start].is_ascii_whitespace() {
+        name_start += 1;
+    }
+    while name_end > name_start && b[name_end - 1].is_ascii_whitespace() {
+        name_end -= 1;
+    }
+    if name_start == name_end {
+        return None;
+    }
+
+    Some(Span {
+        start: name_start,
+        end: name_end,
+    })
+}
+
+const fn bytes_eq(b: &[u8], span: Span, other: &[u8]) -> bool {
+    if span.len() != other.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < other.len() {
+        if b[span.start + i] != other[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Archive;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn agrees_with_parser() {
+        let inputs = [
+            "",
+            "just a comment",
+            "-- simplest.txt --",
+            "comment1\ncomment2\n-- file1 --\nFile 1 text.\n-- foo --\nFile 2 text.\n-- empty --\n-- noNL --\nhello world",
+            "blah\r\n-- hello --\r\nhello\r\n",
+            "--  a  --\n-- b --\nb\n",
+        ];
+
+        for s in inputs {
+            let archive = Archive::from(s);
+            let names: Vec<_> = (0..count(s))
+                .map(|i| nth(s, i).unwrap().name.slice(s))
+                .collect();
+            let expected: Vec<_> = archive
+                .files
+                .iter()
+                .map(|f| f.name.to_str().unwrap())
+                .collect();
+            assert_eq!(names, expected, "names[{s:?}]");
+
+            for (i, file) in archive.files.iter().enumerate() {
+                let mut data = nth(s, i).unwrap().data.slice(s).to_owned();
+                crate::fix_newline(&mut data);
+                assert_eq!(data, file.data, "data[{s:?}][{i}]");
+            }
+
+            let mut comment = super::comment(s).slice(s).to_owned();
+            crate::fix_newline(&mut comment);
+            assert_eq!(comment, archive.comment, "comment[{s:?}]");
+        }
+    }
+}
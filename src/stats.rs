@@ -0,0 +1,85 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::Archive;
+
+/// The number and combined size of files under a directory.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct DirStats {
+    pub files: usize,
+    pub bytes: usize,
+}
+
+impl Archive {
+    /// Aggregates file counts and sizes for every directory up to `depth`
+    /// levels deep.
+    ///
+    /// Each file counts towards all of its parent directories, so the
+    /// empty path holds the totals of the archive and a `depth` of 1
+    /// gives one entry per top-level directory in addition.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use txtar::Archive;
+    ///
+    /// let archive = Archive::from("-- a/x --\n1\n-- a/b/y --\n22\n-- z --\n");
+    /// let stats = archive.stats_by_dir(1);
+    ///
+    /// assert_eq!(stats[Path::new("")].files, 3);
+    /// assert_eq!(stats[Path::new("a")].bytes, 5);
+    /// assert!(!stats.contains_key(Path::new("a/b")));
+    /// ```
+    pub fn stats_by_dir(&self, depth: usize) -> BTreeMap<PathBuf, DirStats> {
+        let mut stats: BTreeMap<PathBuf, DirStats> = BTreeMap::new();
+        for file in &self.files {
+            let parents = file.name.parent().unwrap_or(Path::new(""));
+            let mut dir = PathBuf::new();
+            let mut add = |dir: &Path| {
+                let entry = stats.entry(dir.to_owned()).or_default();
+                entry.files += 1;
+                entry.bytes += file.data.len();
+            };
+
+            add(&dir);
+            for component in parents.components().take(depth) {
+                dir.push(component);
+                add(&dir);
+            }
+        }
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn stats_by_dir() {
+        let archive = Archive::from("-- a/x --\n1\n-- a/b/y --\n22\n-- c/z --\n333\n-- w --\n");
+        let stats = |depth| {
+            archive
+                .stats_by_dir(depth)
+                .into_iter()
+                .map(|(dir, s)| (dir.display().to_string(), s.files, s.bytes))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(stats(0), [("".into(), 4, 9)]);
+        assert_eq!(
+            stats(usize::MAX),
+            [
+                ("".into(), 4, 9),
+                ("a".into(), 2, 5),
+                ("a/b".into(), 1, 3),
+                ("c".into(), 1, 4),
+            ]
+        );
+    }
+}
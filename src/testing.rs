@@ -0,0 +1,84 @@
+/*!
+Assertions for tests that work with archives.
+
+Failures are reported with colored, line-level diffs.
+
+# Examples
+
+```rust
+use txtar::{testing::*, Archive};
+
+let archive = Archive::from("-- go.mod --\nmodule example.com/m\n");
+assert_contains_file(&archive, "go.mod");
+assert_file_eq(&archive, "go.mod", "module example.com/m\n");
+assert_archives_eq(&archive, &Archive::from("-- go.mod --\nmodule example.com/m"));
+```
+!*/
+
+use std::path::Path;
+
+use crate::{Archive, File};
+
+/// Asserts that the archive contains a file with the given name.
+#[track_caller]
+pub fn assert_contains_file<P: AsRef<Path>>(archive: &Archive, name: P) {
+    find(archive, name.as_ref());
+}
+
+/// Asserts that the archive contains a file with the given name and data.
+#[track_caller]
+pub fn assert_file_eq<P: AsRef<Path>>(archive: &Archive, name: P, expected: &str) {
+    let name = name.as_ref();
+    let file = find(archive, name);
+    similar_asserts::assert_eq!(
+        file.data.as_str(),
+        expected,
+        "contents of `{}` differ",
+        name.display()
+    );
+}
+
+/// Asserts that two archives are equal, comparing their txtar form.
+#[track_caller]
+pub fn assert_archives_eq(left: &Archive, right: &Archive) {
+    similar_asserts::assert_eq!(left.to_string(), right.to_string(), "archives differ");
+}
+
+#[track_caller]
+fn find<'a>(archive: &'a Archive, name: &Path) -> &'a File {
+    match archive.files.iter().find(|f| f.name == name) {
+        Some(file) => file,
+        None => {
+            let names: Vec<_> = archive.files.iter().map(|f| f.name.display()).collect();
+            panic!(
+                "archive does not contain `{}`, files are: {names:?}",
+                name.display()
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARCHIVE: &str = "-- a --\nhello\n";
+
+    #[test]
+    #[should_panic(expected = "archive does not contain `b`")]
+    fn missing_file() {
+        assert_contains_file(&Archive::from(ARCHIVE), "b");
+    }
+
+    #[test]
+    #[should_panic(expected = "contents of `a` differ")]
+    fn file_differs() {
+        assert_file_eq(&Archive::from(ARCHIVE), "a", "goodbye\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "archives differ")]
+    fn archives_differ() {
+        assert_archives_eq(&Archive::from(ARCHIVE), &Archive::from("-- a --\n"));
+    }
+}
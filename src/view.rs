@@ -0,0 +1,114 @@
+use std::{iter, ops::RangeBounds, path::Path, slice};
+
+use crate::{Archive, File};
+
+/// A read-only view of some of the files of an archive, borrowed without
+/// copying their contents.
+///
+/// Views are `Send` and `Sync`, so an archive can be split into views
+/// that are processed on separate threads.
+///
+/// This struct is created by [`Archive::view`] and
+/// [`Archive::view_prefix`].
+#[derive(Debug, Clone)]
+pub struct View<'a> {
+    files: Vec<&'a File>,
+}
+
+impl Archive {
+    /// Returns a view of the files in the given range of positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, like slice indexing.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use txtar::Archive;
+    ///
+    /// let archive = Archive::from("-- a --\n-- b --\n-- c --\n-- d --\n");
+    /// let (left, right) = (archive.view(..2), archive.view(2..));
+    /// std::thread::scope(|s| {
+    ///     s.spawn(|| assert_eq!(left.len(), 2));
+    ///     s.spawn(|| assert!(right.get("d").is_some()));
+    /// });
+    /// ```
+    pub fn view<R: RangeBounds<usize>>(&self, range: R) -> View<'_> {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        View {
+            files: self.files[range].iter().collect(),
+        }
+    }
+
+    /// Returns a view of the files whose names start with the given
+    /// prefix, compared component by component as in
+    /// [`Path::starts_with`].
+    pub fn view_prefix<P: AsRef<Path>>(&self, prefix: P) -> View<'_> {
+        let prefix = prefix.as_ref();
+        View {
+            files: self
+                .files
+                .iter()
+                .filter(|f| f.name.starts_with(prefix))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> View<'a> {
+    /// Returns the number of files in the view.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if the view contains no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns the first file in the view with the given name.
+    pub fn get<P: AsRef<Path>>(&self, name: P) -> Option<&'a File> {
+        let name = name.as_ref();
+        self.files.iter().copied().find(|f| f.name == name)
+    }
+
+    /// Returns an iterator over the files in the view.
+    pub fn iter(&self) -> iter::Copied<slice::Iter<'_, &'a File>> {
+        self.files.iter().copied()
+    }
+}
+
+impl<'a, 'v> IntoIterator for &'v View<'a> {
+    type Item = &'a File;
+    type IntoIter = iter::Copied<slice::Iter<'v, &'a File>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn views() {
+        let archive = Archive::from("-- a/1 --\n-- b --\n-- a/2 --\n-- ab --\n");
+        let names = |view: &View| -> Vec<String> {
+            view.iter()
+                .map(|f| f.name().display().to_string())
+                .collect()
+        };
+
+        assert_eq!(names(&archive.view(1..3)), ["b", "a/2"]);
+        assert_eq!(names(&archive.view(..)), ["a/1", "b", "a/2", "ab"]);
+        assert!(archive.view(4..).is_empty());
+
+        let view = archive.view_prefix("a");
+        assert_eq!(names(&view), ["a/1", "a/2"]);
+        assert!(view.get("ab").is_none());
+        assert!(std::ptr::eq(view.get("a/2").unwrap(), &archive.files[2]));
+    }
+}
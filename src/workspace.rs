@@ -0,0 +1,111 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use clean_path::Clean;
+
+use crate::MaterializeError;
+
+/// Returns the root of the Cargo workspace that is being built or tested.
+///
+/// The search starts at `CARGO_MANIFEST_DIR`, which Cargo sets when it
+/// runs tests and build scripts, or the current directory otherwise. The
+/// root is the closest directory above it whose `Cargo.toml` has a
+/// `[workspace]` table, or else the closest directory with a
+/// `Cargo.toml`.
+///
+/// # Errors
+///
+/// This function will error with [`io::ErrorKind::NotFound`] if no
+/// `Cargo.toml` is found.
+pub fn workspace_root() -> io::Result<PathBuf> {
+    let start = match env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => env::current_dir()?,
+    };
+    let start = start.canonicalize()?;
+
+    let mut package = None;
+    for dir in start.ancestors() {
+        let Ok(manifest) = fs::read_to_string(dir.join("Cargo.toml")) else {
+            continue;
+        };
+        if manifest.lines().any(|l| l.trim() == "[workspace]") {
+            return Ok(dir.to_owned());
+        }
+        package.get_or_insert_with(|| dir.to_owned());
+    }
+
+    package.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{}: not inside a Cargo package", start.display()),
+        )
+    })
+}
+
+/// Checks that the directory at `path` is inside the Cargo workspace or
+/// the temporary directory.
+pub(crate) fn check_in_workspace(path: &Path) -> Result<(), MaterializeError> {
+    let root = workspace_root()?;
+    let path = resolve(path)?;
+    let tmp = resolve(&env::temp_dir())?;
+    if path.starts_with(&root) || path.starts_with(tmp) {
+        return Ok(());
+    }
+
+    Err(MaterializeError::OutsideWorkspace(
+        path.to_string_lossy().to_string(),
+        root.to_string_lossy().to_string(),
+    ))
+}
+
+/// Resolves symlinks in the closest ancestor of `path` that exists, and
+/// appends the rest of the path to it.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let path = env::current_dir()?.join(path).clean();
+    let mut missing = Vec::new();
+    let mut existing = path.as_path();
+    let resolved = loop {
+        match existing.canonicalize() {
+            Ok(resolved) => break resolved,
+            Err(_) => match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    missing.push(name);
+                    existing = parent;
+                }
+                _ => return Ok(path),
+            },
+        }
+    };
+
+    Ok(missing.iter().rev().fold(resolved, |p, name| p.join(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Archive, MaterializeOptions};
+    use similar_asserts::assert_eq;
+
+    #[test]
+    fn workspace_guard() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .canonicalize()
+            .unwrap();
+        assert_eq!(workspace_root().unwrap(), manifest_dir);
+
+        let archive = Archive::from("-- a --\n");
+        let options = MaterializeOptions::new().workspace_only(true);
+        let dir = assert_fs::TempDir::new().unwrap();
+        archive
+            .materialize_with(dir.path().join("new"), &options)
+            .unwrap();
+
+        let outside = manifest_dir.join("target/../../txtar-workspace-guard");
+        let err = archive.materialize_with(&outside, &options).unwrap_err();
+        assert!(matches!(err, MaterializeError::OutsideWorkspace(..)));
+        assert!(!outside.exists());
+    }
+}